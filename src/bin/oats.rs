@@ -1,13 +1,13 @@
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{IsTerminal, Read, Write};
 
-use oat_scheme::{eval, parse, Environment, Value};
+use oat_scheme::{eval, Environment, Error, Value};
 
-fn run(input: &str, env: &mut Environment) {
-    let values = match parse(input) {
+fn run(name: &str, input: &str, env: &mut Environment) {
+    let values = match env.parse_source(name, input) {
         Ok(v) => v,
         Err(err) => {
-            println!("error: {err}");
+            report(&err);
             return;
         }
     };
@@ -19,13 +19,16 @@ fn run(input: &str, env: &mut Environment) {
                     println!("{}", value)
                 }
             }
-            Err(err) => {
-                println!("error: {}", err);
-            }
+            Err(err) => report(&err),
         }
     }
 }
 
+/// Prints `err` to stderr, in color if it is a terminal.
+fn report(err: &Error) {
+    eprint!("{}", err.report(std::io::stderr().is_terminal()));
+}
+
 fn repl() {
     let mut env = Environment::default();
 
@@ -36,19 +39,38 @@ fn repl() {
         let mut input = String::new();
         std::io::stdin().read_line(&mut input).unwrap();
 
-        run(&input, &mut env)
+        run("<stdin>", &input, &mut env)
     }
 }
 
-fn main() {
-    if let Some(path) = std::env::args().nth(1) {
-        let mut file = File::open(path).unwrap();
+fn read_file(path: &str) -> String {
+    let mut file = File::open(path).unwrap();
 
-        let mut input = String::new();
-        file.read_to_string(&mut input).unwrap();
+    let mut input = String::new();
+    file.read_to_string(&mut input).unwrap();
+
+    input
+}
 
-        run(&input, &mut Environment::default());
-    } else {
-        repl();
+/// Reports every syntax error in the file at `path` without evaluating it, exiting with a
+/// failure status if there are any.
+fn check(path: &str) {
+    if let Err(err) = Environment::default().parse_source(path, &read_file(path)) {
+        report(&err);
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.as_slice() {
+        [flag, path] if flag == "--check" => check(path),
+        [path] => run(path, &read_file(path), &mut Environment::default()),
+        [] => repl(),
+        _ => {
+            eprintln!("usage: oats [--check] [FILE]");
+            std::process::exit(2);
+        }
     }
 }
@@ -1,128 +1,438 @@
+use std::collections::HashSet;
+
 use gc::Gc;
 
-use crate::environment::Environment;
-use crate::error::{Error, Result};
-use crate::eval::eval_to_value;
+use crate::error::{Error, Result, Type};
+use crate::library::Library;
 use crate::unscheme;
-use crate::value::{PrimitiveProcedure, Value};
+use crate::value::{PrimitiveProcedure, PrimitiveProcedureFunction, Value};
+
+mod char;
+mod control;
+mod format;
+mod hash_table;
+mod io;
+mod list;
+#[cfg(feature = "regex")]
+mod regexp;
+mod sort;
+mod string;
+mod vector;
 
 pub fn builtins() -> impl Iterator<Item = (String, Gc<Value>)> {
-    BUILTINS.iter().map(|&(s, f)| {
+    [
+        BUILTINS,
+        char::BUILTINS,
+        control::BUILTINS,
+        format::BUILTINS,
+        hash_table::BUILTINS,
+        io::BUILTINS,
+        list::BUILTINS,
+        #[cfg(feature = "regex")]
+        regexp::BUILTINS,
+        sort::BUILTINS,
+        string::BUILTINS,
+        vector::BUILTINS,
+    ]
+    .into_iter()
+    .flatten()
+    .map(|&(name, function)| {
         (
-            s.to_owned(),
-            Gc::new(Value::PrimitiveProcedure(PrimitiveProcedure(f))),
+            name.to_owned(),
+            Gc::new(Value::PrimitiveProcedure(PrimitiveProcedure {
+                name,
+                function,
+            })),
         )
     })
 }
 
-type PrimitiveProcedureFunction = fn(&Gc<Value>, &mut Environment) -> Result<Gc<Value>>;
-
 const BUILTINS: &[(&str, PrimitiveProcedureFunction)] = &[
-    ("display", |params, env| {
-        let value = unscheme!(params, env ==> [any])?;
-        match *value {
-            Value::String(ref s) => println!("{}", s),
-            Value::Character(c) => println!("{}", c),
-            _ => println!("{}", value),
-        }
+    ("load-library", |args, env| {
+        let name = unscheme!(args => &[Symbol])?;
+        let library = Library::from_name(&name).ok_or(Error::UnknownLibrary(name))?;
+        env.load_library(library)?;
         Ok(Value::void())
     }),
-    ("not", |params, env| {
-        let b = unscheme!(params, env ==> [Boolean])?;
+    ("not", |args, _| {
+        let b = unscheme!(args => &[Boolean])?;
         Ok(Value::boolean(!b))
     }),
-    ("eq?", |params, env| {
-        let (lhs, rhs) = unscheme!(params, env ==> [any, any])?;
+    ("eq?", |args, _| {
+        let (lhs, rhs) = unscheme!(args => &[any, any])?;
         Ok(Value::boolean(eq(&lhs, &rhs)))
     }),
-    ("cons", |params, env| {
-        let (car, cdr) = unscheme!(params, env ==> [any, any])?;
+    ("eqv?", |args, _| {
+        let (lhs, rhs) = unscheme!(args => &[any, any])?;
+        Ok(Value::boolean(eqv(&lhs, &rhs)))
+    }),
+    ("equal?", |args, _| {
+        let (lhs, rhs) = unscheme!(args => &[any, any])?;
+        Ok(Value::boolean(equal(&lhs, &rhs)))
+    }),
+    ("cons", |args, _| {
+        let (car, cdr) = unscheme!(args => &[any, any])?;
         Ok(Value::pair(&car, &cdr))
     }),
-    ("car", |params, env| {
-        let (car, _cdr) = unscheme!(params, env ==> [Pair])?;
+    ("car", |args, _| {
+        let (car, _cdr) = unscheme!(args => &[Pair])?;
         Ok(car)
     }),
-    ("cdr", |params, env| {
-        let (_car, cdr) = unscheme!(params, env ==> [Pair])?;
+    ("cdr", |args, _| {
+        let (_car, cdr) = unscheme!(args => &[Pair])?;
         Ok(cdr)
     }),
-    ("list", |params, env| {
-        Ok(Gc::new(
-            params
-                .as_ref()
-                .map(|p| eval_to_value(p?, env))
-                .collect::<Result<_>>()?,
-        ))
+    ("list", |args, _env| {
+        Ok(Gc::new(args.iter().cloned().collect()))
     }),
-    ("abs", |params, env| {
-        Ok(Value::number(unscheme!(params, env ==> [Number])?.abs()))
+    ("abs", |args, _| {
+        Ok(Value::number(unscheme!(args => &[Number])?.abs()))
     }),
-    ("+", |params, env| {
-        let numbers = params.map(|p| unscheme!(p?, env ==> Number));
+    ("+", |args, _| {
+        let numbers = args.iter().map(|arg| unscheme!(arg => Number));
         Ok(Value::number(numbers.sum::<Result<_>>()?))
     }),
-    ("-", |params, env| match params.count() {
-        0 => Err(Error::IncorrectArity(1, 0)),
-        1 => Ok(Value::number(-unscheme!(params, env ==> [Number])?)),
+    ("-", |args, _| match args.len() {
+        0 => Err(Error::arity(1, 0)),
+        1 => Ok(Value::number(-unscheme!(args => &[Number])?)),
         _ => {
-            let (minuend, rest) = unscheme!(params, env ==> [Number, rest])?;
+            let (minuend, rest) = unscheme!(args => &[Number, rest])?;
             let subtrahend = rest
-                .as_ref()
-                .try_fold(0.0, |acc, p| Ok(acc + unscheme!(p?, env ==> Number)?))?;
+                .iter()
+                .try_fold(0.0, |acc, arg| Ok(acc + unscheme!(arg => Number)?))?;
             Ok(Value::number(minuend - subtrahend))
         }
     }),
-    ("*", |params, env| {
-        let numbers = params.map(|p| unscheme!(p?, env ==> Number));
+    ("*", |args, _| {
+        let numbers = args.iter().map(|arg| unscheme!(arg => Number));
         Ok(Value::number(numbers.product::<Result<_>>()?))
     }),
-    ("/", |params, env| match params.count() {
-        0 => Err(Error::IncorrectArity(1, 0)),
-        1 => Ok(Value::number(1.0 / unscheme!(params, env ==> [Number])?)),
+    ("/", |args, _| match args.len() {
+        0 => Err(Error::arity(1, 0)),
+        1 => Ok(Value::number(1.0 / unscheme!(args => &[Number])?)),
         _ => {
-            let (dividend, rest) = unscheme!(params, env ==> [Number, rest])?;
+            let (dividend, rest) = unscheme!(args => &[Number, rest])?;
             let divisor = rest
-                .as_ref()
-                .try_fold(1.0, |acc, p| Ok(acc * unscheme!(&p?, env ==> Number)?))?;
+                .iter()
+                .try_fold(1.0, |acc, arg| Ok(acc * unscheme!(arg => Number)?))?;
             Ok(Value::number(dividend / divisor))
         }
     }),
-    ("string-length", |params, env| {
-        let string = unscheme!(params, env ==> [String])?;
-        Ok(Value::number(string.chars().count() as f64))
-    }),
-    ("string-ref", |params, env| {
-        let (string, idx) = unscheme!(params, env ==> [String, Number])?;
-        let c = string.chars().nth(idx as usize);
-        Ok(Value::character(
-            c.ok_or(Error::IndexOutOfBounds(idx as usize))?,
-        ))
-    }),
-    ("substring", |params, env| {
-        let (string, (start, end)) = unscheme!(params, env ==> [String, Number, Number])?;
-        Ok(Value::string(&string[start as usize..end as usize]))
-    }),
-    ("string-append", |params, env| {
-        let string = params
-            .map(|p| unscheme!(p?, env ==> String))
-            .collect::<Result<String>>()?;
-        Ok(Gc::new(Value::String(string)))
-    }),
+    ("=", |args, _| compare(args, |a, b| a == b)),
+    ("<", |args, _| compare(args, |a, b| a < b)),
+    (">", |args, _| compare(args, |a, b| a > b)),
+    ("<=", |args, _| compare(args, |a, b| a <= b)),
+    (">=", |args, _| compare(args, |a, b| a >= b)),
 ];
 
-fn eq(lhs: &Value, rhs: &Value) -> bool {
-    match (lhs, rhs) {
-        (Value::Symbol(ref l), Value::Symbol(ref r)) => l == r,
-        (Value::Number(ref l), Value::Number(ref r)) => l == r,
-        (Value::String(ref l), Value::String(ref r)) => l == r,
-        (Value::Character(ref l), Value::Character(ref r)) => l == r,
-        (Value::Boolean(ref l), Value::Boolean(ref r)) => l == r,
+/// Checks that `cmp` holds between each adjacent pair of numbers in `args`.
+fn compare(args: &[Gc<Value>], cmp: fn(f64, f64) -> bool) -> Result<Gc<Value>> {
+    let numbers = args
+        .iter()
+        .map(|arg| unscheme!(arg => Number))
+        .collect::<Result<Vec<_>>>()?;
+
+    if numbers.is_empty() {
+        return Err(Error::arity(1, 0));
+    }
+
+    Ok(Value::boolean(numbers.windows(2).all(|w| cmp(w[0], w[1]))))
+}
+
+/// Converts `k` to an index or length, which must be a non-negative integer.
+fn to_index(k: f64) -> Result<usize> {
+    if k >= 0.0 && k.fract() == 0.0 && k <= u32::MAX.into() {
+        Ok(k as usize)
+    } else {
+        Err(Error::TypeMismatch(Type::Index, Value::number(k)))
+    }
+}
+
+/// The longest vector, list or string that `make-vector`, `make-list` and `make-string` will
+/// allocate, so that a large length is reported as an error rather than aborting the process.
+const MAX_LENGTH: usize = 1 << 24;
+
+/// Converts `k` to the length of a new vector, list or string, which must be at most [`MAX_LENGTH`].
+fn to_length(k: f64) -> Result<usize> {
+    let k = to_index(k)?;
+    if k <= MAX_LENGTH {
+        Ok(k)
+    } else {
+        Err(Error::LengthTooLarge {
+            length: k,
+            max: MAX_LENGTH,
+        })
+    }
+}
+
+/// Iterates over the elements of `lists` in lockstep, stopping at the end of the shortest list.
+fn zip_lists(lists: &[Gc<Value>]) -> impl Iterator<Item = Result<Vec<Gc<Value>>>> + '_ {
+    let mut iters: Vec<_> = lists.iter().map(|list| list.iter()).collect();
+    std::iter::from_fn(move || iters.iter_mut().map(Iterator::next).collect())
+}
+
+/// Object identity, as used by `eq?`.
+///
+/// Symbols are compared by name, as if they were interned, while booleans and the empty list
+/// compare by value. Every other value is only `eq?` to itself, i.e. when both refer to the same
+/// [`Gc`] allocation.
+pub(crate) fn eq(lhs: &Gc<Value>, rhs: &Gc<Value>) -> bool {
+    match (&**lhs, &**rhs) {
+        (Value::Symbol(l), Value::Symbol(r)) => l == r,
+        (Value::Boolean(l), Value::Boolean(r)) => l == r,
         (Value::EmptyList, Value::EmptyList) => true,
-        (Value::Pair(_), Value::Pair(_)) => lhs.zip(rhs).all(|(l, r)| match l {
-            Ok(l) => r.is_ok_and(|r| eq(&l, &r)),
-            Err(_) => r.is_err(),
-        }),
-        _ => false,
+        (Value::Eof, Value::Eof) => true,
+        _ => Gc::ptr_eq(lhs, rhs),
+    }
+}
+
+/// Like [`eq`], but numbers and characters are compared by value, as used by `eqv?`.
+pub(crate) fn eqv(lhs: &Gc<Value>, rhs: &Gc<Value>) -> bool {
+    match (&**lhs, &**rhs) {
+        (Value::Number(l), Value::Number(r)) => l == r,
+        (Value::Character(l), Value::Character(r)) => l == r,
+        _ => eq(lhs, rhs),
+    }
+}
+
+/// Structural equality, as used by `equal?`.
+///
+/// Pairs, vectors and strings are compared recursively, falling back to [`eqv`] for everything else.
+/// Terminates on cyclic data, treating two structures as equal if no difference can be found
+/// by unfolding them.
+pub(crate) fn equal(lhs: &Gc<Value>, rhs: &Gc<Value>) -> bool {
+    equal_inner(lhs.clone(), rhs.clone(), &mut HashSet::new())
+}
+
+fn equal_inner(
+    mut lhs: Gc<Value>,
+    mut rhs: Gc<Value>,
+    visited: &mut HashSet<(*const Value, *const Value)>,
+) -> bool {
+    loop {
+        let ((lcar, lcdr), (rcar, rcdr)) = match (&*lhs, &*rhs) {
+            (Value::String(l), Value::String(r)) => return *l.borrow() == *r.borrow(),
+            (Value::Vector(l), Value::Vector(r)) => {
+                let (l, r) = (l.borrow().clone(), r.borrow().clone());
+                if l.len() != r.len() {
+                    return false;
+                }
+                if !visited.insert((&*lhs, &*rhs)) {
+                    return true;
+                }
+                return l
+                    .into_iter()
+                    .zip(r)
+                    .all(|(l, r)| equal_inner(l, r, visited));
+            }
+            (Value::Pair(l), Value::Pair(r)) => (l.borrow().clone(), r.borrow().clone()),
+            _ => return eqv(&lhs, &rhs),
+        };
+
+        // Already being compared further up, so any difference will be found there
+        if !visited.insert((&*lhs, &*rhs)) {
+            return true;
+        }
+        if !equal_inner(lcar, rcar, visited) {
+            return false;
+        }
+
+        (lhs, rhs) = (lcdr, rcdr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use gc::Gc;
+
+    use super::builtins;
+    use crate::error::{Error, Result};
+    use crate::{apply, eval, parse_one, run, scheme, Environment, Value};
+
+    fn check(input: &str, expected: bool) -> Result<()> {
+        let env = &mut Environment::default();
+        assert_eq!(
+            eval(parse_one(input)?, env)?,
+            Value::boolean(expected),
+            "{input}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn equivalence_predicates() -> Result<()> {
+        check("(eq? 'a 'a)", true)?;
+        check("(eq? '() '())", true)?;
+        check("(eq? car car)", true)?;
+        check("(eq? \"a\" \"a\")", false)?;
+        check("(eq? '(a) '(a))", false)?;
+        check("(eq? 1 1)", false)?;
+
+        check("(eqv? 1 1)", true)?;
+        check("(eqv? #\\a #\\a)", true)?;
+        check("(eqv? 1 2)", false)?;
+        check("(eqv? \"a\" \"a\")", false)?;
+        check("(eqv? '(a) '(a))", false)?;
+
+        check("(equal? \"a\" \"a\")", true)?;
+        check("(equal? '(a (b \"c\") 1) '(a (b \"c\") 1))", true)?;
+        check("(equal? '(a b) '(a b c))", false)?;
+        check("(equal? #(1 (2)) #(1 (2)))", true)?;
+        check("(equal? #(1 2) #(1 2 3))", false)?;
+        check("(eqv? #() #())", false)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn numeric_comparisons() -> Result<()> {
+        check("(< 1 2 3)", true)?;
+        check("(< 1 3 2)", false)?;
+        check("(<= 1 1 2)", true)?;
+        check("(> 3 2 1)", true)?;
+        check("(>= 1 2)", false)?;
+        check("(= 2 2.0)", true)?;
+        check("(= 1)", true)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn substring() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(substring \"aλbc\" 1 3)", "\"λb\"");
+        assert_eval!(env, "(substring \"aλbc\" 4 4)", "\"\"");
+        assert!(matches!(
+            run("(substring \"aλbc\" 2 5)", env),
+            Err(Error::IndexOutOfBounds(5))
+        ));
+        assert!(matches!(
+            run("(substring \"aλbc\" 3 2)", env),
+            Err(Error::IndexOutOfBounds(3))
+        ));
+        assert!(matches!(
+            run("(substring \"aλbc\" -1 2)", env),
+            Err(Error::TypeMismatch(..))
+        ));
+        assert!(matches!(
+            run("(string-ref \"aλbc\" 1.5)", env),
+            Err(Error::TypeMismatch(..))
+        ));
+
+        Ok(())
+    }
+
+    /// Values of many types, including edge cases such as negative, fractional and non-finite
+    /// numbers and multibyte strings.
+    const ARGUMENTS: &[&str] = &[
+        "0",
+        "1",
+        "-1",
+        "2.5",
+        "1000",
+        "4294967295",
+        "(/ 0 0)",
+        "(/ 1 0)",
+        "\"\"",
+        "\"aλb\"",
+        "#\\λ",
+        "'a",
+        "#f",
+        "'()",
+        "'(1 2 3)",
+        "(cons 1 2)",
+        "(vector 1 \"a\")",
+        "(make-hash-table)",
+        "car",
+        "(lambda args args)",
+        "\"(\"",
+        "(eof-object)",
+        "(open-input-string \"a\")",
+        "(open-output-string)",
+    ];
+
+    /// A smaller set of values for three-argument calls.
+    const FEWER_ARGUMENTS: &[&str] = &[
+        "-1",
+        "2.5",
+        "(/ 1 0)",
+        "\"aλb\"",
+        "#\\λ",
+        "'(1 2 3)",
+        "(vector 1 \"a\")",
+        "(lambda args args)",
+    ];
+
+    #[test]
+    fn evaluated_arguments() -> Result<()> {
+        let env = &mut Environment::default();
+
+        // Arguments are passed through as they are, without being evaluated again
+        let args = vec![Value::symbol("undefined"), scheme!(car x), Value::void()];
+        let list = apply(&env.get("list")?, args.clone(), env)?;
+        assert_eq!(list, Gc::new(args.into_iter().collect()));
+
+        assert!(matches!(
+            apply(&env.get("car")?, vec![], env),
+            Err(Error::IncorrectArity {
+                expected: 1,
+                found: 0,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    /// Builtins that create files, which would litter the working directory.
+    const UNFUZZED: &[&str] = &[
+        "open-output-file",
+        "open-binary-output-file",
+        "call-with-output-file",
+        "with-output-to-file",
+    ];
+
+    #[test]
+    fn no_builtin_panics() {
+        for (name, procedure) in builtins() {
+            if UNFUZZED.contains(&name.as_str()) {
+                continue;
+            }
+
+            // Fresh values for each builtin, as some of them are mutated
+            let env = &mut Environment::default();
+            env.set_stdin(std::io::empty());
+            env.set_stdout(std::io::sink());
+            env.set_stderr(std::io::sink());
+            let mut values = |inputs: &[&str]| -> Vec<Gc<Value>> {
+                inputs
+                    .iter()
+                    .map(|input| run(input, env).unwrap())
+                    .collect()
+            };
+            let (all, fewer) = (values(ARGUMENTS), values(FEWER_ARGUMENTS));
+
+            let mut calls = vec![vec![]];
+            calls.extend(all.iter().map(|a| vec![a.clone()]));
+            for a in &all {
+                calls.extend(all.iter().map(|b| vec![a.clone(), b.clone()]));
+            }
+            for a in &fewer {
+                for b in &fewer {
+                    calls.extend(fewer.iter().map(|c| vec![a.clone(), b.clone(), c.clone()]));
+                }
+            }
+
+            for args in calls {
+                let call: Value = std::iter::once(Value::symbol(&name))
+                    .chain(args.iter().cloned())
+                    .collect();
+                let result = catch_unwind(AssertUnwindSafe(|| apply(&procedure, args, env)));
+                assert!(result.is_ok(), "{call} panicked");
+            }
+        }
     }
 }
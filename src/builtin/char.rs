@@ -0,0 +1,202 @@
+use gc::Gc;
+
+use super::PrimitiveProcedureFunction;
+use crate::error::{Error, Result};
+use crate::unscheme;
+use crate::value::Value;
+
+pub const BUILTINS: &[(&str, PrimitiveProcedureFunction)] = &[
+    ("char?", |args, _| {
+        let value = unscheme!(args => &[any])?;
+        Ok(Value::boolean(matches!(*value, Value::Character(_))))
+    }),
+    ("char=?", |args, _| compare(args, false, |a, b| a == b)),
+    ("char<?", |args, _| compare(args, false, |a, b| a < b)),
+    ("char>?", |args, _| compare(args, false, |a, b| a > b)),
+    ("char<=?", |args, _| compare(args, false, |a, b| a <= b)),
+    ("char>=?", |args, _| compare(args, false, |a, b| a >= b)),
+    ("char-ci=?", |args, _| compare(args, true, |a, b| a == b)),
+    ("char-ci<?", |args, _| compare(args, true, |a, b| a < b)),
+    ("char-ci>?", |args, _| compare(args, true, |a, b| a > b)),
+    ("char-ci<=?", |args, _| compare(args, true, |a, b| a <= b)),
+    ("char-ci>=?", |args, _| compare(args, true, |a, b| a >= b)),
+    ("char-alphabetic?", |args, _| {
+        let c = unscheme!(args => &[Character])?;
+        Ok(Value::boolean(c.is_alphabetic()))
+    }),
+    ("char-numeric?", |args, _| {
+        let c = unscheme!(args => &[Character])?;
+        Ok(Value::boolean(digit_value(c).is_some()))
+    }),
+    ("char-whitespace?", |args, _| {
+        let c = unscheme!(args => &[Character])?;
+        Ok(Value::boolean(c.is_whitespace()))
+    }),
+    ("char-upper-case?", |args, _| {
+        let c = unscheme!(args => &[Character])?;
+        Ok(Value::boolean(c.is_uppercase()))
+    }),
+    ("char-lower-case?", |args, _| {
+        let c = unscheme!(args => &[Character])?;
+        Ok(Value::boolean(c.is_lowercase()))
+    }),
+    ("char-upcase", |args, _| {
+        let c = unscheme!(args => &[Character])?;
+        Ok(Value::character(upcase(c)))
+    }),
+    ("char-downcase", |args, _| {
+        let c = unscheme!(args => &[Character])?;
+        Ok(Value::character(downcase(c)))
+    }),
+    ("char-foldcase", |args, _| {
+        let c = unscheme!(args => &[Character])?;
+        Ok(Value::character(downcase(c)))
+    }),
+    ("digit-value", |args, _| {
+        let c = unscheme!(args => &[Character])?;
+        Ok(match digit_value(c) {
+            Some(digit) => Value::number(digit.into()),
+            None => Value::boolean(false),
+        })
+    }),
+    ("char->integer", |args, _| {
+        let c = unscheme!(args => &[Character])?;
+        Ok(Value::number(u32::from(c).into()))
+    }),
+    ("integer->char", |args, _| {
+        let n = unscheme!(args => &[Number])?;
+        let c = Some(n)
+            .filter(|n| n.fract() == 0.0 && (0.0..=u32::MAX.into()).contains(n))
+            .and_then(|n| char::from_u32(n as u32));
+        Ok(Value::character(c.ok_or(Error::InvalidCodePoint(n))?))
+    }),
+];
+
+/// The first of each run of ten decimal digits, from zero to nine, in the Unicode general
+/// category Nd as of Unicode 16.0.
+const DIGIT_ZEROS: &[u32] = &[
+    0x30, 0x660, 0x6F0, 0x7C0, 0x966, 0x9E6, 0xA66, 0xAE6, 0xB66, 0xBE6, 0xC66, 0xCE6, 0xD66,
+    0xDE6, 0xE50, 0xED0, 0xF20, 0x1040, 0x1090, 0x17E0, 0x1810, 0x1946, 0x19D0, 0x1A80, 0x1A90,
+    0x1B50, 0x1BB0, 0x1C40, 0x1C50, 0xA620, 0xA8D0, 0xA900, 0xA9D0, 0xA9F0, 0xAA50, 0xABF0, 0xFF10,
+    0x104A0, 0x10D30, 0x10D40, 0x11066, 0x110F0, 0x11136, 0x111D0, 0x112F0, 0x11450, 0x114D0,
+    0x11650, 0x116C0, 0x116D0, 0x116DA, 0x11730, 0x118E0, 0x11950, 0x11BF0, 0x11C50, 0x11D50,
+    0x11DA0, 0x11F50, 0x16130, 0x16A60, 0x16AC0, 0x16B50, 0x16D70, 0x1CCF0, 0x1D7CE, 0x1D7D8,
+    0x1D7E2, 0x1D7EC, 0x1D7F6, 0x1E140, 0x1E2F0, 0x1E4F0, 0x1E5F1, 0x1E950, 0x1FBF0,
+];
+
+/// The value of `c` if it is a decimal digit in any script, as used by `digit-value` and
+/// `char-numeric?`.
+fn digit_value(c: char) -> Option<u32> {
+    let c = u32::from(c);
+    let zero = DIGIT_ZEROS[DIGIT_ZEROS
+        .partition_point(|&zero| zero <= c)
+        .checked_sub(1)?];
+    Some(c - zero).filter(|&digit| digit < 10)
+}
+
+/// Converts `c` to upper case, leaving it unchanged if its upper case form is not a single
+/// character, e.g. `ß`.
+pub(crate) fn upcase(c: char) -> char {
+    single(c.to_uppercase()).unwrap_or(c)
+}
+
+/// Converts `c` to lower case, leaving it unchanged if its lower case form is not a single
+/// character.
+pub(crate) fn downcase(c: char) -> char {
+    single(c.to_lowercase()).unwrap_or(c)
+}
+
+fn single(mut chars: impl Iterator<Item = char>) -> Option<char> {
+    let c = chars.next()?;
+    chars.next().is_none().then_some(c)
+}
+
+/// Checks that `cmp` holds between each adjacent pair of characters in `args`, comparing
+/// them case-insensitively if `fold` is set.
+fn compare(args: &[Gc<Value>], fold: bool, cmp: fn(char, char) -> bool) -> Result<Gc<Value>> {
+    let chars = args
+        .iter()
+        .map(|p| unscheme!(p => Character))
+        .map(|c| c.map(|c| if fold { downcase(c) } else { c }))
+        .collect::<Result<Vec<_>>>()?;
+
+    if chars.is_empty() {
+        return Err(Error::arity(1, 0));
+    }
+
+    Ok(Value::boolean(chars.windows(2).all(|w| cmp(w[0], w[1]))))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, Result};
+    use crate::{run, Environment};
+
+    #[test]
+    fn predicates() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(char? #\\a)", "#t");
+        assert_eval!(env, "(char? \"a\")", "#f");
+        assert_eval!(env, "(char-alphabetic? #\\λ)", "#t");
+        assert_eval!(env, "(char-alphabetic? #\\1)", "#f");
+        assert_eval!(env, "(char-numeric? #\\٣)", "#t");
+        assert_eval!(env, "(char-numeric? #\\½)", "#f");
+        assert_eval!(env, "(char-numeric? #\\x2162)", "#f");
+        assert_eval!(env, "(char-whitespace? #\\space)", "#t");
+        assert_eval!(env, "(char-whitespace? #\\ )", "#t");
+        assert_eval!(env, "(char-upper-case? #\\Σ)", "#t");
+        assert_eval!(env, "(char-lower-case? #\\Σ)", "#f");
+
+        Ok(())
+    }
+
+    #[test]
+    fn comparisons() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(char=? #\\a #\\a #\\a)", "#t");
+        assert_eval!(env, "(char<? #\\a #\\b #\\c)", "#t");
+        assert_eval!(env, "(char<? #\\a #\\c #\\b)", "#f");
+        assert_eval!(env, "(char>=? #\\b #\\b #\\a)", "#t");
+        assert_eval!(env, "(char=? #\\a #\\A)", "#f");
+        assert_eval!(env, "(char-ci=? #\\a #\\A)", "#t");
+        assert_eval!(env, "(char-ci<? #\\a #\\B)", "#t");
+        assert!(matches!(
+            run("(char<? #\\a 1)", env),
+            Err(Error::TypeMismatch(..))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn conversions() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(char-upcase #\\a)", "#\\A");
+        assert_eval!(env, "(char-upcase #\\ß)", "#\\ß");
+        assert_eval!(env, "(char-downcase #\\Ä)", "#\\ä");
+        assert_eval!(env, "(char-foldcase #\\Σ)", "#\\σ");
+        assert_eval!(env, "(digit-value #\\7)", "7");
+        assert_eval!(env, "(digit-value #\\a)", "#f");
+        assert_eval!(env, "(digit-value #\\x0663)", "3");
+        assert_eval!(env, "(digit-value #\\x1D7D9)", "1");
+        assert_eval!(env, "(digit-value #\\x0669)", "9");
+        assert_eval!(env, "(digit-value #\\x066A)", "#f");
+        assert_eval!(env, "(digit-value #\\x2162)", "#f");
+        assert_eval!(env, "(char->integer #\\A)", "65");
+        assert_eval!(env, "(char->integer #\\λ)", "955");
+        assert_eval!(env, "(integer->char 955)", "#\\λ");
+        assert!(matches!(
+            run("(integer->char 55296)", env),
+            Err(Error::InvalidCodePoint(_))
+        ));
+        assert!(matches!(
+            run("(integer->char -1)", env),
+            Err(Error::InvalidCodePoint(_))
+        ));
+
+        Ok(())
+    }
+}
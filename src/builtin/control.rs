@@ -0,0 +1,85 @@
+use gc::Gc;
+
+use super::{zip_lists, PrimitiveProcedureFunction};
+use crate::error::{Error, Result};
+use crate::eval::apply;
+use crate::unscheme;
+use crate::value::Value;
+
+pub const BUILTINS: &[(&str, PrimitiveProcedureFunction)] = &[
+    ("procedure?", |args, _| {
+        let value = unscheme!(args => &[any])?;
+        Ok(Value::boolean(matches!(
+            *value,
+            Value::PrimitiveProcedure(_) | Value::Procedure(_)
+        )))
+    }),
+    ("apply", |args, env| {
+        let (procedure, rest) = unscheme!(args => &[any, rest])?;
+
+        let mut args = rest.to_vec();
+
+        let last = args.pop().ok_or(Error::arity(2, 1))?;
+        for arg in last.iter() {
+            args.push(arg?);
+        }
+
+        apply(&procedure, args, env)
+    }),
+    ("map", |args, env| {
+        let (procedure, lists) = unscheme!(args => &[any, rest])?;
+
+        if lists.is_empty() {
+            return Err(Error::arity(2, 1));
+        }
+
+        let results = zip_lists(lists)
+            .map(|args| apply(&procedure, args?, env))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Gc::new(results.into_iter().collect()))
+    }),
+    ("for-each", |args, env| {
+        let (procedure, lists) = unscheme!(args => &[any, rest])?;
+
+        if lists.is_empty() {
+            return Err(Error::arity(2, 1));
+        }
+
+        for args in zip_lists(lists) {
+            apply(&procedure, args?, env)?;
+        }
+
+        Ok(Value::void())
+    }),
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Result;
+    use crate::{run, Environment};
+
+    #[test]
+    fn higher_order() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(apply + 1 2 '(3 4))", "10");
+        assert_eval!(env, "(apply list '())", "()");
+        assert_eval!(env, "(map (lambda (x) (* x x)) '(1 2 3))", "(1 4 9)");
+        assert_eval!(env, "(map + '(1 2 3) '(10 20))", "(11 22)");
+        assert_eval!(env, "(map cadr '((a b) (c d)))", "(b d)");
+        assert_eval!(env, "(procedure? car)", "#t");
+        assert_eval!(env, "(procedure? 'car)", "#f");
+
+        run(
+            "
+            (define table (make-hash-table))
+            (for-each (lambda (k v) (hash-table-set! table k v)) '(a b) '(1 2))
+            ",
+            env,
+        )?;
+        assert_eval!(env, "(hash-table-ref table 'b)", "2");
+
+        Ok(())
+    }
+}
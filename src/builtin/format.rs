@@ -0,0 +1,287 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use gc::Gc;
+
+use super::PrimitiveProcedureFunction;
+use crate::error::{Error, Result, Type};
+use crate::unscheme;
+use crate::value::{Style, Value};
+
+pub const BUILTINS: &[(&str, PrimitiveProcedureFunction)] = &[("format", |args, env| {
+    let (destination, args) = unscheme!(args => &[any, rest])?;
+
+    let (port, template, args) = match *destination {
+        // `(format "...")` is shorthand for `(format #f "...")`
+        Value::String(ref s) => (None, s.borrow().clone(), args),
+        _ => {
+            let port = match *destination {
+                Value::Boolean(false) => None,
+                Value::Boolean(true) => Some(env.output_port()),
+                Value::Port(ref port) if port.is_output() && !port.is_binary() => {
+                    Some(port.clone())
+                }
+                _ => return Err(Error::TypeMismatch(Type::TextualOutputPort, destination)),
+            };
+            let template = args.first().ok_or(Error::arity(2, 1))?;
+            (port, unscheme!(template => String)?, &args[1..])
+        }
+    };
+
+    let output = format(&template, args)?;
+
+    match port {
+        Some(port) => {
+            port.write_str(&output)?;
+            Ok(Value::void())
+        }
+        None => Ok(Value::string(&output)),
+    }
+})];
+
+/// Expands the directives in `template`, consuming a value from `args` for each of `~a`, `~s`,
+/// `~d`, `~x` and `~b`.
+///
+/// Each of these may be preceded by a minimum width, to which the output is padded on the left,
+/// and `~d` additionally by a precision, e.g. `~8,2d`.
+fn format(template: &str, args: &[Gc<Value>]) -> Result<String> {
+    let mut output = String::new();
+    let mut args = args.iter();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '~' {
+            output.push(c);
+            continue;
+        }
+
+        let width = parameter(&mut chars)?;
+        let precision = match chars.next_if_eq(&',') {
+            Some(_) => Some(parameter(&mut chars)?.ok_or_else(|| {
+                Error::InvalidFormat("expected a precision after `,`".to_owned())
+            })?),
+            None => None,
+        };
+
+        let directive = chars.next().ok_or_else(|| {
+            Error::InvalidFormat("incomplete directive at end of format string".to_owned())
+        })?;
+
+        if width.is_some() && matches!(directive, '%' | '~') {
+            return Err(Error::InvalidFormat(format!(
+                "`~{directive}` does not take a width"
+            )));
+        }
+
+        if precision.is_some() && directive != 'd' {
+            return Err(Error::InvalidFormat(format!(
+                "`~{directive}` does not take a precision"
+            )));
+        }
+
+        let mut next = || {
+            args.next()
+                .ok_or_else(|| Error::InvalidFormat(format!("missing argument for `~{directive}`")))
+        };
+
+        let text = match directive {
+            'a' => next()?.styled(Style::Display).to_string(),
+            's' => next()?.to_string(),
+            'd' => {
+                let value = next()?;
+                let n = unscheme!(value => Number)?;
+                match precision {
+                    Some(precision) if n.is_finite() => format!("{n:.precision$}"),
+                    _ => value.to_string(),
+                }
+            }
+            'x' => {
+                let (sign, magnitude) = integer(next()?)?;
+                format!("{sign}{magnitude:x}")
+            }
+            'b' => {
+                let (sign, magnitude) = integer(next()?)?;
+                format!("{sign}{magnitude:b}")
+            }
+            '%' => "\n".to_owned(),
+            '~' => "~".to_owned(),
+            _ => {
+                return Err(Error::InvalidFormat(format!(
+                    "unknown format directive `~{directive}`"
+                )))
+            }
+        };
+
+        let padding = width.unwrap_or(0).saturating_sub(text.chars().count());
+        output.extend(std::iter::repeat_n(' ', padding));
+        output.push_str(&text);
+    }
+
+    if args.next().is_some() {
+        return Err(Error::InvalidFormat(
+            "too many arguments for format string".to_owned(),
+        ));
+    }
+
+    Ok(output)
+}
+
+/// The largest width or precision a directive may have.
+const MAX_PARAMETER: usize = u16::MAX as usize;
+
+/// Reads the decimal digits of a directive parameter, if there are any.
+fn parameter(chars: &mut Peekable<Chars>) -> Result<Option<usize>> {
+    let mut digits = String::new();
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        digits.push(digit);
+    }
+
+    if digits.is_empty() {
+        return Ok(None);
+    }
+
+    match digits.parse() {
+        Ok(n) if n <= MAX_PARAMETER => Ok(Some(n)),
+        _ => Err(Error::InvalidFormat(format!(
+            "`{digits}` is larger than the maximum width or precision of {MAX_PARAMETER}"
+        ))),
+    }
+}
+
+/// Splits the integer `value` into its sign and magnitude.
+fn integer(value: &Gc<Value>) -> Result<(&'static str, u64)> {
+    let n = unscheme!(value => Number)?;
+
+    // `u64::MAX as f64` rounds up to 2^64, which is itself too large
+    if n.fract() != 0.0 || n.abs() >= u64::MAX as f64 {
+        return Err(Error::TypeMismatch(Type::Integer, value.clone()));
+    }
+
+    let sign = if n < 0.0 { "-" } else { "" };
+    Ok((sign, n.abs() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, Result};
+    use crate::{run, Environment};
+
+    #[test]
+    fn directives() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(format \"plain\")", "\"plain\"");
+        assert_eval!(
+            env,
+            "(format #f \"~a and ~s\" \"x\" \"y\")",
+            "\"x and \\\"y\\\"\""
+        );
+        assert_eval!(env, "(format #f \"~a ~s\" #\\c #\\c)", "\"c #\\\\c\"");
+        assert_eval!(env, "(format #f \"~a\" '(1 \"b\" #\\c))", "\"(1 b c)\"");
+        assert_eval!(
+            env,
+            "(format #f \"~s\" '(1 \"b\" #\\c))",
+            "\"(1 \\\"b\\\" #\\\\c)\""
+        );
+        assert_eval!(env, "(format #f \"~d ~d\" 42 1.5)", "\"42 1.5\"");
+        assert_eval!(
+            env,
+            "(format #f \"~d ~d ~,2d\" (/ 1. 0) (/ 0. 0) (/ -1. 0))",
+            "\"+inf.0 +nan.0 -inf.0\""
+        );
+        assert_eval!(
+            env,
+            "(format #f \"~x\" 18446744073709549568)",
+            "\"fffffffffffff800\""
+        );
+        assert_eval!(env, "(format #f \"~x ~b\" 255 -5)", "\"ff -101\"");
+        assert_eval!(env, "(format #f \"50~~~%\")", "\"50~\\n\"");
+
+        Ok(())
+    }
+
+    #[test]
+    fn width_and_precision() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(format #f \"[~5d]\" 42)", "\"[   42]\"");
+        assert_eval!(env, "(format #f \"[~8,2d]\" 3.14159)", "\"[    3.14]\"");
+        assert_eval!(env, "(format #f \"[~,3d]\" 2)", "\"[2.000]\"");
+        assert_eval!(env, "(format #f \"[~4x]\" 255)", "\"[  ff]\"");
+        assert_eval!(env, "(format #f \"[~3a]\" \"λ\")", "\"[  λ]\"");
+        assert_eval!(env, "(format #f \"[~1a]\" \"long\")", "\"[long]\"");
+        assert_eval!(env, "(string-length (format #f \"~65535a\" 1))", "65535");
+
+        Ok(())
+    }
+
+    #[test]
+    fn destinations() -> Result<()> {
+        let env = &mut Environment::default();
+        let path = std::env::temp_dir().join(format!("oat-scheme-{}-format", std::process::id()));
+
+        run(&format!("(define path {:?})", path.to_str().unwrap()), env)?;
+        run(
+            "(call-with-output-file path (lambda (port) (format port \"~a-~s\" 1 \"2\")))",
+            env,
+        )?;
+        assert_eval!(
+            env,
+            "(call-with-input-file path read-line)",
+            "\"1-\\\"2\\\"\""
+        );
+
+        run(
+            "(with-output-to-file path (lambda () (format #t \"~~~%\")))",
+            env,
+        )?;
+        assert_eval!(env, "(call-with-input-file path read-line)", "\"~\"");
+
+        std::fs::remove_file(path).unwrap();
+
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        let env = &mut Environment::default();
+
+        for input in [
+            "(format #f \"~a\")",
+            "(format #f \"~a\" 1 2)",
+            "(format #f \"~q\" 1)",
+            "(format #f \"~\")",
+            "(format #f \"~2,1a\" 1)",
+            "(format #f \"~3%\")",
+            "(format #f \"~,999999999d\" 1)",
+            "(format #f \"~999999999a\" 1)",
+            "(format #f \"~99999999999999999999999a\" 1)",
+        ] {
+            assert!(
+                matches!(run(input, env), Err(Error::InvalidFormat(_))),
+                "{input}"
+            );
+        }
+
+        assert!(matches!(
+            run("(format #f \"~d\" \"1\")", env),
+            Err(Error::TypeMismatch(..))
+        ));
+        assert!(matches!(
+            run("(format #f \"~x\" 1.5)", env),
+            Err(Error::TypeMismatch(..))
+        ));
+        assert!(matches!(
+            run("(format #f \"~x\" 18446744073709551616)", env),
+            Err(Error::TypeMismatch(..))
+        ));
+        assert!(matches!(
+            run("(format (open-input-file \"Cargo.toml\") \"~a\" 1)", env),
+            Err(Error::TypeMismatch(..))
+        ));
+        assert!(matches!(
+            run("(format 1 \"~a\" 1)", env),
+            Err(Error::TypeMismatch(..))
+        ));
+    }
+}
@@ -0,0 +1,213 @@
+use gc::{Gc, GcCell};
+
+use super::PrimitiveProcedureFunction;
+use crate::environment::Environment;
+use crate::error::{Error, Result};
+use crate::eval::apply;
+use crate::hash_table::{Equivalence, HashTable};
+use crate::unscheme;
+use crate::value::Value;
+
+pub const BUILTINS: &[(&str, PrimitiveProcedureFunction)] = &[
+    ("make-hash-table", |args, _env| {
+        make_hash_table(args, Equivalence::Equal)
+    }),
+    ("make-equal-hash-table", |args, _env| {
+        make_hash_table(args, Equivalence::Equal)
+    }),
+    ("make-eqv-hash-table", |args, _env| {
+        make_hash_table(args, Equivalence::Eqv)
+    }),
+    ("make-eq-hash-table", |args, _env| {
+        make_hash_table(args, Equivalence::Eq)
+    }),
+    ("hash-table?", |args, _| {
+        let value = unscheme!(args => &[any])?;
+        Ok(Value::boolean(matches!(*value, Value::HashTable(_))))
+    }),
+    ("hash-table-ref", |args, env| match args.len() {
+        2 => {
+            let (table, key) = unscheme!(args => &[HashTable, any])?;
+            lookup(&table, key, None, env)
+        }
+        3 => {
+            let (table, (key, thunk)) = unscheme!(args => &[HashTable, any, any])?;
+            lookup(&table, key, Some(&thunk), env)
+        }
+        n => Err(Error::arity(3, n)),
+    }),
+    ("hash-table-ref/default", |args, _| {
+        let (table, (key, default)) = unscheme!(args => &[HashTable, any, any])?;
+        let value = table.borrow().get(&key);
+        Ok(value.unwrap_or(default))
+    }),
+    ("hash-table-set!", |args, _| {
+        let (table, (key, value)) = unscheme!(args => &[HashTable, any, any])?;
+        table.borrow_mut().insert(key, value);
+        Ok(Value::void())
+    }),
+    ("hash-table-delete!", |args, _| {
+        let (table, key) = unscheme!(args => &[HashTable, any])?;
+        table.borrow_mut().remove(&key);
+        Ok(Value::void())
+    }),
+    ("hash-table-contains?", |args, _| {
+        let (table, key) = unscheme!(args => &[HashTable, any])?;
+        let contains = table.borrow().get(&key).is_some();
+        Ok(Value::boolean(contains))
+    }),
+    ("hash-table-count", |args, _| {
+        let table = unscheme!(args => &[HashTable])?;
+        let len = table.borrow().len();
+        Ok(Value::number(len as f64))
+    }),
+    ("hash-table-update!", |args, env| {
+        let (table, (key, (procedure, thunk))) = match args.len() {
+            3 => {
+                let (table, (key, procedure)) = unscheme!(args => &[HashTable, any, any])?;
+                (table, (key, (procedure, None)))
+            }
+            4 => {
+                let (table, (key, (procedure, thunk))) =
+                    unscheme!(args => &[HashTable, any, any, any])?;
+                (table, (key, (procedure, Some(thunk))))
+            }
+            n => return Err(Error::arity(4, n)),
+        };
+
+        let value = lookup(&table, key.clone(), thunk.as_ref(), env)?;
+        let value = apply(&procedure, vec![value], env)?;
+        table.borrow_mut().insert(key, value);
+
+        Ok(Value::void())
+    }),
+    ("hash-table-keys", |args, _| {
+        let table = unscheme!(args => &[HashTable])?;
+        let keys = table.borrow().iter().map(|(k, _)| k.clone()).collect();
+        Ok(Gc::new(keys))
+    }),
+    ("hash-table-values", |args, _| {
+        let table = unscheme!(args => &[HashTable])?;
+        let values = table.borrow().iter().map(|(_, v)| v.clone()).collect();
+        Ok(Gc::new(values))
+    }),
+    ("hash-table->alist", |args, _| {
+        let table = unscheme!(args => &[HashTable])?;
+        let alist = table
+            .borrow()
+            .iter()
+            .map(|(k, v)| Value::pair(k, v))
+            .collect();
+        Ok(Gc::new(alist))
+    }),
+    ("hash-table-walk", |args, env| {
+        let (table, procedure) = unscheme!(args => &[HashTable, any])?;
+
+        // Take a snapshot so that `procedure` is free to modify the table
+        let entries: Vec<_> = table
+            .borrow()
+            .iter()
+            .map(|(k, v)| vec![k.clone(), v.clone()])
+            .collect();
+
+        for args in entries {
+            apply(&procedure, args, env)?;
+        }
+
+        Ok(Value::void())
+    }),
+];
+
+fn make_hash_table(args: &[Gc<Value>], equivalence: Equivalence) -> Result<Gc<Value>> {
+    match args.len() {
+        0 => Ok(Value::hash_table(equivalence)),
+        n => Err(Error::arity(0, n)),
+    }
+}
+
+/// Looks up `key` in `table`, calling `thunk` if it is not present.
+fn lookup(
+    table: &GcCell<HashTable>,
+    key: Gc<Value>,
+    thunk: Option<&Gc<Value>>,
+    env: &mut Environment,
+) -> Result<Gc<Value>> {
+    let value = table.borrow().get(&key);
+
+    match (value, thunk) {
+        (Some(value), _) => Ok(value),
+        (None, Some(thunk)) => apply(thunk, Vec::new(), env),
+        (None, None) => Err(Error::KeyNotFound(key)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Result;
+    use crate::{run, scheme, Environment};
+
+    #[test]
+    fn equivalence() -> Result<()> {
+        let env = &mut Environment::default();
+
+        run(
+            "
+            (define equal-table (make-hash-table))
+            (define eqv-table (make-eqv-hash-table))
+            (define key (list 1 2))
+            (hash-table-set! equal-table key 'a)
+            (hash-table-set! eqv-table key 'a)
+            ",
+            env,
+        )?;
+
+        assert_eq!(
+            run("(hash-table-ref equal-table (list 1 2))", env)?,
+            scheme!({ a }),
+        );
+        assert_eq!(run("(hash-table-ref eqv-table key)", env)?, scheme!({ a }));
+        assert!(run("(hash-table-ref eqv-table (list 1 2))", env).is_err());
+        assert_eq!(
+            run("(hash-table-ref eqv-table (list 1 2) (lambda () 'b))", env)?,
+            scheme!({ b }),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mutation() -> Result<()> {
+        let env = &mut Environment::default();
+
+        run(
+            "
+            (define table (make-hash-table))
+            (hash-table-set! table \"a\" 1)
+            (hash-table-set! table 'b 2)
+            (hash-table-set! table 'b 3)
+            (hash-table-update! table \"a\" (lambda (x) (+ x 10)))
+            (hash-table-update! table 'c (lambda (x) (+ x 1)) (lambda () 0))
+            (hash-table-delete! table 'b)
+            ",
+            env,
+        )?;
+
+        assert_eq!(run("(hash-table-count table)", env)?, scheme!({ 2 }));
+        assert_eq!(run("(hash-table-ref table \"a\")", env)?, scheme!({ 11 }));
+        assert_eq!(run("(hash-table-ref table 'c)", env)?, scheme!({ 1 }));
+        assert_eq!(
+            run("(hash-table-ref/default table 'b 'none)", env)?,
+            scheme!({ none }),
+        );
+
+        run(
+            "(hash-table-walk table (lambda (k v) (hash-table-set! table k (* v 2))))",
+            env,
+        )?;
+
+        assert_eq!(run("(hash-table-ref table 'c)", env)?, scheme!({ 2 }));
+        assert_eq!(run("(hash-table-ref table \"a\")", env)?, scheme!({ 22 }));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,612 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use gc::Gc;
+
+use super::{to_index, PrimitiveProcedureFunction};
+use crate::environment::Environment;
+use crate::error::{Error, Result, Type};
+use crate::eval::apply;
+use crate::port::Port;
+use crate::unscheme;
+use crate::value::{Style, Value};
+
+pub const BUILTINS: &[(&str, PrimitiveProcedureFunction)] = &[
+    ("display", |args, env| print(args, env, Style::Display)),
+    ("write", |args, env| print(args, env, Style::Write)),
+    ("write-shared", |args, env| print(args, env, Style::Shared)),
+    ("newline", |args, env| {
+        output_port(args, 0, false, env)?.write_str("\n")?;
+        Ok(Value::void())
+    }),
+    ("write-char", |args, env| {
+        let (c, rest) = unscheme!(args => &[Character, rest])?;
+        output_port(rest, 1, false, env)?.write_str(c.encode_utf8(&mut [0; 4]))?;
+        Ok(Value::void())
+    }),
+    ("write-string", |args, env| {
+        let (string, rest) = unscheme!(args => &[String, rest])?;
+        output_port(rest, 1, false, env)?.write_str(&string)?;
+        Ok(Value::void())
+    }),
+    ("write-u8", |args, env| {
+        let (byte, rest) = unscheme!(args => &[Number, rest])?;
+        let byte = Some(byte)
+            .filter(|b| b.fract() == 0.0 && (0.0..=255.0).contains(b))
+            .ok_or_else(|| Error::TypeMismatch(Type::Byte, Value::number(byte)))?;
+        output_port(rest, 1, true, env)?.write_u8(byte as u8)?;
+        Ok(Value::void())
+    }),
+    ("flush-output-port", |args, env| {
+        output_port(args, 0, false, env)?.flush()?;
+        Ok(Value::void())
+    }),
+    ("read", |args, env| {
+        let datum = input_port(args, 0, false, env)?.read()?;
+        Ok(datum.unwrap_or_else(Value::eof))
+    }),
+    ("read-char", |args, env| {
+        let c = input_port(args, 0, false, env)?.read_char()?;
+        Ok(c.map_or_else(Value::eof, Value::character))
+    }),
+    ("peek-char", |args, env| {
+        let c = input_port(args, 0, false, env)?.peek_char()?;
+        Ok(c.map_or_else(Value::eof, Value::character))
+    }),
+    ("read-line", |args, env| {
+        let line = input_port(args, 0, false, env)?.read_line()?;
+        Ok(line.map_or_else(Value::eof, |line| Value::string(&line)))
+    }),
+    ("read-string", |args, env| {
+        let (k, rest) = unscheme!(args => &[Number, rest])?;
+        let string = input_port(rest, 1, false, env)?.read_string(to_index(k)?)?;
+        Ok(string.map_or_else(Value::eof, |string| Value::string(&string)))
+    }),
+    ("read-u8", |args, env| {
+        let byte = input_port(args, 0, true, env)?.read_u8()?;
+        Ok(byte.map_or_else(Value::eof, |byte| Value::number(byte.into())))
+    }),
+    ("peek-u8", |args, env| {
+        let byte = input_port(args, 0, true, env)?.peek_u8()?;
+        Ok(byte.map_or_else(Value::eof, |byte| Value::number(byte.into())))
+    }),
+    ("char-ready?", |args, env| {
+        let ready = input_port(args, 0, false, env)?.is_ready()?;
+        Ok(Value::boolean(ready))
+    }),
+    ("u8-ready?", |args, env| {
+        let ready = input_port(args, 0, true, env)?.is_ready()?;
+        Ok(Value::boolean(ready))
+    }),
+    ("eof-object", |args, _| match args.len() {
+        0 => Ok(Value::eof()),
+        n => Err(Error::arity(0, n)),
+    }),
+    ("eof-object?", |args, _| {
+        let value = unscheme!(args => &[any])?;
+        Ok(Value::boolean(matches!(*value, Value::Eof)))
+    }),
+    ("port?", |args, _| {
+        let value = unscheme!(args => &[any])?;
+        Ok(Value::boolean(matches!(*value, Value::Port(_))))
+    }),
+    ("input-port?", |args, _| is_port(args, Port::is_input)),
+    ("output-port?", |args, _| is_port(args, Port::is_output)),
+    ("textual-port?", |args, _| {
+        is_port(args, |port| !port.is_binary())
+    }),
+    ("binary-port?", |args, _| is_port(args, Port::is_binary)),
+    ("input-port-open?", |args, _| {
+        let port = expect_port(&unscheme!(args => &[any])?, Type::InputPort, Port::is_input)?;
+        Ok(Value::boolean(port.is_open()))
+    }),
+    ("output-port-open?", |args, _| {
+        let port = expect_port(
+            &unscheme!(args => &[any])?,
+            Type::OutputPort,
+            Port::is_output,
+        )?;
+        Ok(Value::boolean(port.is_open()))
+    }),
+    ("open-input-file", |args, _| open_input_file(args, false)),
+    ("open-binary-input-file", |args, _| {
+        open_input_file(args, true)
+    }),
+    ("open-output-file", |args, _| open_output_file(args, false)),
+    ("open-binary-output-file", |args, _| {
+        open_output_file(args, true)
+    }),
+    ("close-port", |args, _| {
+        let port = expect_port(&unscheme!(args => &[any])?, Type::Port, |_| true)?;
+        port.close()?;
+        Ok(Value::void())
+    }),
+    ("close-input-port", |args, _| {
+        let port = expect_port(&unscheme!(args => &[any])?, Type::InputPort, Port::is_input)?;
+        port.close()?;
+        Ok(Value::void())
+    }),
+    ("close-output-port", |args, _| {
+        let port = expect_port(
+            &unscheme!(args => &[any])?,
+            Type::OutputPort,
+            Port::is_output,
+        )?;
+        port.close()?;
+        Ok(Value::void())
+    }),
+    ("call-with-port", |args, env| {
+        let (port, procedure) = unscheme!(args => &[any, any])?;
+        call_with_port(port, &procedure, env)
+    }),
+    ("call-with-input-file", |args, env| {
+        let (filename, procedure) = unscheme!(args => &[String, any])?;
+        let port = Value::port(Port::file_input(
+            File::open(filename).map_err(Error::io)?,
+            false,
+        ));
+        call_with_port(port, &procedure, env)
+    }),
+    ("call-with-output-file", |args, env| {
+        let (filename, procedure) = unscheme!(args => &[String, any])?;
+        let port = Value::port(Port::output(
+            BufWriter::new(File::create(filename).map_err(Error::io)?),
+            false,
+        ));
+        call_with_port(port, &procedure, env)
+    }),
+    ("with-input-from-file", |args, env| {
+        let (filename, thunk) = unscheme!(args => &[String, any])?;
+        let port = Gc::new(Port::file_input(
+            File::open(filename).map_err(Error::io)?,
+            false,
+        ));
+
+        let previous = env.replace_input_port(port.clone());
+        let result = apply(&thunk, vec![], env);
+        env.replace_input_port(previous);
+
+        port.close()?;
+        result
+    }),
+    ("with-output-to-file", |args, env| {
+        let (filename, thunk) = unscheme!(args => &[String, any])?;
+        let port = Gc::new(Port::output(
+            BufWriter::new(File::create(filename).map_err(Error::io)?),
+            false,
+        ));
+
+        let result = with_output_port(&port, &thunk, env);
+        port.close()?;
+        result
+    }),
+    ("current-input-port", |args, env| match args.len() {
+        0 => Ok(Gc::new(Value::Port(env.input_port()))),
+        n => Err(Error::arity(0, n)),
+    }),
+    ("current-output-port", |args, env| match args.len() {
+        0 => Ok(Gc::new(Value::Port(env.output_port()))),
+        n => Err(Error::arity(0, n)),
+    }),
+    ("current-error-port", |args, env| match args.len() {
+        0 => Ok(Gc::new(Value::Port(env.error_port()))),
+        n => Err(Error::arity(0, n)),
+    }),
+    ("open-input-string", |args, _| {
+        let string = unscheme!(args => &[String])?;
+        Ok(Value::port(Port::string_input(&string)))
+    }),
+    ("open-output-string", |args, _| match args.len() {
+        0 => Ok(Value::port(Port::string_output())),
+        n => Err(Error::arity(0, n)),
+    }),
+    ("get-output-string", |args, _| {
+        let value = unscheme!(args => &[any])?;
+        let port = expect_port(&value, Type::StringOutputPort, Port::is_output)?;
+        let string = port.output_string()?;
+        let string = string.ok_or(Error::TypeMismatch(Type::StringOutputPort, value))?;
+        Ok(Value::string(&string))
+    }),
+    ("with-output-to-string", |args, env| {
+        let thunk = unscheme!(args => &[any])?;
+        let port = Gc::new(Port::string_output());
+        with_output_port(&port, &thunk, env)?;
+        Ok(Value::string(&port.output_string()?.unwrap_or_default()))
+    }),
+    ("call-with-output-string", |args, env| {
+        let procedure = unscheme!(args => &[any])?;
+        let port = Gc::new(Port::string_output());
+        apply(&procedure, vec![Gc::new(Value::Port(port.clone()))], env)?;
+        Ok(Value::string(&port.output_string()?.unwrap_or_default()))
+    }),
+];
+
+/// Writes the value in `args` in the given style, to the port following it or otherwise the
+/// current output port.
+fn print(args: &[Gc<Value>], env: &mut Environment, style: Style) -> Result<Gc<Value>> {
+    let (value, rest) = unscheme!(args => &[any, rest])?;
+    let port = output_port(rest, 1, false, env)?;
+    port.write_str(&value.styled(style).to_string())?;
+    Ok(Value::void())
+}
+
+/// Returns the textual or binary input port given by the optional argument `rest`, which follows
+/// `required` other arguments, or the current input port if it is absent.
+fn input_port(
+    rest: &[Gc<Value>],
+    required: usize,
+    binary: bool,
+    env: &mut Environment,
+) -> Result<Gc<Port>> {
+    let port = match rest {
+        [] => env.input_port(),
+        [value] => expect_port(value, Type::InputPort, Port::is_input)?,
+        _ => return Err(Error::arity(required + 1, required + rest.len())),
+    };
+    expect_kind(port, binary)
+}
+
+/// Like [`input_port`], but for output ports.
+fn output_port(
+    rest: &[Gc<Value>],
+    required: usize,
+    binary: bool,
+    env: &mut Environment,
+) -> Result<Gc<Port>> {
+    let port = match rest {
+        [] => env.output_port(),
+        [value] => expect_port(value, Type::OutputPort, Port::is_output)?,
+        _ => return Err(Error::arity(required + 1, required + rest.len())),
+    };
+    expect_kind(port, binary)
+}
+
+/// Checks that `value` is a port satisfying `predicate`, described by `expected` if it is not.
+fn expect_port(
+    value: &Gc<Value>,
+    expected: Type,
+    predicate: fn(&Port) -> bool,
+) -> Result<Gc<Port>> {
+    match &**value {
+        Value::Port(port) if predicate(port) => Ok(port.clone()),
+        _ => Err(Error::TypeMismatch(expected, value.clone())),
+    }
+}
+
+/// Checks that `port` is binary if `binary` is set, or textual otherwise.
+fn expect_kind(port: Gc<Port>, binary: bool) -> Result<Gc<Port>> {
+    if port.is_binary() == binary {
+        return Ok(port);
+    }
+
+    let expected = if binary {
+        Type::BinaryPort
+    } else {
+        Type::TextualPort
+    };
+    Err(Error::TypeMismatch(expected, Gc::new(Value::Port(port))))
+}
+
+fn is_port(args: &[Gc<Value>], predicate: fn(&Port) -> bool) -> Result<Gc<Value>> {
+    let value = unscheme!(args => &[any])?;
+    Ok(Value::boolean(match *value {
+        Value::Port(ref port) => predicate(port),
+        _ => false,
+    }))
+}
+
+fn open_input_file(args: &[Gc<Value>], binary: bool) -> Result<Gc<Value>> {
+    let filename = unscheme!(args => &[String])?;
+    let file = File::open(filename).map_err(Error::io)?;
+    Ok(Value::port(Port::file_input(file, binary)))
+}
+
+fn open_output_file(args: &[Gc<Value>], binary: bool) -> Result<Gc<Value>> {
+    let filename = unscheme!(args => &[String])?;
+    let file = File::create(filename).map_err(Error::io)?;
+    Ok(Value::port(Port::output(BufWriter::new(file), binary)))
+}
+
+/// Calls `thunk` with `port` as the current output port, restoring the previous one afterwards.
+fn with_output_port(
+    port: &Gc<Port>,
+    thunk: &Gc<Value>,
+    env: &mut Environment,
+) -> Result<Gc<Value>> {
+    let previous = env.replace_output_port(port.clone());
+    let result = apply(thunk, vec![], env);
+    env.replace_output_port(previous);
+    result
+}
+
+/// Calls `procedure` with `port`, closing the port once it returns.
+fn call_with_port(
+    port: Gc<Value>,
+    procedure: &Gc<Value>,
+    env: &mut Environment,
+) -> Result<Gc<Value>> {
+    let inner = expect_port(&port, Type::Port, |_| true)?;
+    let result = apply(procedure, vec![port], env);
+    inner.close()?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, Result};
+    use crate::value::Style;
+    use crate::{run, Environment};
+
+    /// A path in the temporary directory that is unique to this process and `name`.
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("oat-scheme-{}-{name}", std::process::id()));
+        path.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn styles() -> Result<()> {
+        let env = &mut Environment::default();
+
+        let value = run("(list 'a \"b c\" #\\d '|e f| #(1.5 \"g\"))", env)?;
+        assert_eq!(
+            value.styled(Style::Display).to_string(),
+            "(a b c d e f #(1.5 g))"
+        );
+        assert_eq!(
+            value.styled(Style::Write).to_string(),
+            "(a \"b c\" #\\d |e f| #(1.5 \"g\"))"
+        );
+        assert_eq!(value.to_string(), value.styled(Style::Write).to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn datum_labels() -> Result<()> {
+        let env = &mut Environment::default();
+
+        run(
+            "
+            (define shared (list 1 2))
+            (define tree (list shared shared))
+            (define cycle (list 1 2 3))
+            (set-cdr! (cddr cycle) cycle)
+            (define v (vector 'a 'b))
+            (vector-set! v 1 v)
+            (define nested (list 'x cycle))
+            (define tail (cons 'x cycle))
+            ",
+            env,
+        )?;
+
+        let mut print =
+            |input, style| -> Result<String> { Ok(run(input, env)?.styled(style).to_string()) };
+
+        assert_eq!(print("tree", Style::Write)?, "((1 2) (1 2))");
+        assert_eq!(print("tree", Style::Shared)?, "(#0=(1 2) #0#)");
+        assert_eq!(print("cycle", Style::Write)?, "#0=(1 2 3 . #0#)");
+        assert_eq!(print("cycle", Style::Display)?, "#0=(1 2 3 . #0#)");
+        assert_eq!(print("v", Style::Write)?, "#0=#(a #0#)");
+        assert_eq!(print("nested", Style::Write)?, "(x #0=(1 2 3 . #0#))");
+        assert_eq!(print("tail", Style::Write)?, "(x . #0=(1 2 3 . #0#))");
+        assert_eq!(print("''a", Style::Write)?, "'a");
+
+        Ok(())
+    }
+
+    #[test]
+    fn textual_files() -> Result<()> {
+        let env = &mut Environment::default();
+        let path = temp_path("textual");
+
+        run(&format!("(define path \"{path}\")"), env)?;
+        run(
+            "
+            (call-with-output-file path
+              (lambda (port)
+                (write-string \"λ line\" port)
+                (newline port)
+                (write \"quoted\" port)
+                (write-char #\\! port)))
+            ",
+            env,
+        )?;
+        run("(define port (open-input-file path))", env)?;
+
+        assert_eval!(env, "(input-port? port)", "#t");
+        assert_eval!(env, "(textual-port? port)", "#t");
+        assert_eval!(env, "(char-ready? port)", "#t");
+        assert_eval!(env, "(peek-char port)", "#\\λ");
+        assert_eval!(env, "(read-char port)", "#\\λ");
+        assert_eval!(env, "(read-line port)", "\" line\"");
+        assert_eval!(env, "(read-string 3 port)", "\"\\\"qu\"");
+        assert_eval!(env, "(read-line port)", "\"oted\\\"!\"");
+        assert_eval!(env, "(eof-object? (read-line port))", "#t");
+        assert_eval!(env, "(eof-object? (peek-char port))", "#t");
+        assert_eval!(env, "(eof-object? (read-string 1 port))", "#t");
+
+        run("(close-port port)", env)?;
+        assert_eval!(env, "(input-port-open? port)", "#f");
+        assert!(matches!(
+            run("(read-char port)", env),
+            Err(Error::ClosedPort)
+        ));
+
+        run(
+            "(with-output-to-file path (lambda () (display \"a\") (display #\\b) (newline)))",
+            env,
+        )?;
+        assert_eval!(env, "(with-input-from-file path read-line)", "\"ab\"");
+        assert_eval!(env, "(call-with-input-file path read-char)", "#\\a");
+
+        std::fs::remove_file(path).unwrap();
+
+        Ok(())
+    }
+
+    #[test]
+    fn binary_files() -> Result<()> {
+        let env = &mut Environment::default();
+        let path = temp_path("binary");
+
+        run(&format!("(define path \"{path}\")"), env)?;
+        run("(define out (open-binary-output-file path))", env)?;
+        run(
+            "(write-u8 0 out) (write-u8 255 out) (close-output-port out)",
+            env,
+        )?;
+        run("(define in (open-binary-input-file path))", env)?;
+
+        assert_eval!(env, "(binary-port? in)", "#t");
+        assert_eval!(env, "(peek-u8 in)", "0");
+        assert_eval!(env, "(read-u8 in)", "0");
+        assert_eval!(env, "(read-u8 in)", "255");
+        assert_eval!(env, "(eof-object? (read-u8 in))", "#t");
+
+        assert!(matches!(
+            run("(read-char in)", env),
+            Err(Error::TypeMismatch(..))
+        ));
+        assert!(matches!(
+            run("(write-u8 256 (open-binary-output-file path))", env),
+            Err(Error::TypeMismatch(..))
+        ));
+
+        std::fs::remove_file(path).unwrap();
+
+        assert!(matches!(
+            run("(open-input-file path)", env),
+            Err(Error::Io(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn string_ports() -> Result<()> {
+        let env = &mut Environment::default();
+
+        run("(define in (open-input-string \"λ one\\ntwo\"))", env)?;
+        assert_eval!(env, "(read-char in)", "#\\λ");
+        assert_eval!(env, "(read-line in)", "\" one\"");
+        assert_eval!(env, "(read-string 10 in)", "\"two\"");
+        assert_eval!(env, "(eof-object? (read-char in))", "#t");
+
+        run("(define out (open-output-string))", env)?;
+        run("(write 'a out) (display \" \" out) (write \"b\" out)", env)?;
+        assert_eval!(env, "(get-output-string out)", "\"a \\\"b\\\"\"");
+        run("(write-char #\\c out)", env)?;
+        assert_eval!(env, "(get-output-string out)", "\"a \\\"b\\\"c\"");
+
+        assert_eval!(
+            env,
+            "(with-output-to-string (lambda () (display 1) (newline) (write-string \"x\")))",
+            "\"1\\nx\""
+        );
+        assert_eval!(
+            env,
+            "(call-with-output-string (lambda (port) (format port \"~a~a\" 1 2)))",
+            "\"12\""
+        );
+        assert_eval!(
+            env,
+            "(with-output-to-string (lambda () (write (output-port? (current-output-port)))))",
+            "\"#t\""
+        );
+
+        assert!(matches!(
+            run("(get-output-string (current-output-port))", env),
+            Err(Error::TypeMismatch(..))
+        ));
+        assert!(matches!(
+            run("(get-output-string in)", env),
+            Err(Error::TypeMismatch(..))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn ready() -> Result<()> {
+        let env = &mut Environment::default();
+
+        // A host reader may block, so it is only ready once a character has been peeked
+        env.set_stdin(std::io::Cursor::new("ab"));
+        assert_eval!(env, "(char-ready?)", "#f");
+        assert_eval!(env, "(peek-char)", "#\\a");
+        assert_eval!(env, "(char-ready?)", "#t");
+        assert_eval!(env, "(read-char)", "#\\a");
+        assert_eval!(env, "(char-ready?)", "#f");
+
+        assert_eval!(env, "(char-ready? (open-input-string \"\"))", "#t");
+        run("(define in (open-input-string \"\"))", env)?;
+        run("(close-port in)", env)?;
+        let err = run("(char-ready? in)", env).unwrap_err();
+        assert!(matches!(err.root(), Error::ClosedPort), "{err:?}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn read() -> Result<()> {
+        let env = &mut Environment::default();
+
+        run(
+            "(define in (open-input-string \"  (a\\n  \\\"b\\nc\\\" #(1 2)) 'x 3.5\\n(after) #\\\\(\"))",
+            env,
+        )?;
+        assert_eval!(env, "(read in)", "(a \"b\\nc\" #(1 2))");
+        assert_eval!(env, "(read in)", "'x");
+        assert_eval!(env, "(read-char in)", "#\\space");
+        assert_eval!(env, "(read in)", "3.5");
+        assert_eval!(env, "(read in)", "(after)");
+        assert_eval!(env, "(read in)", "#\\(");
+        assert_eval!(env, "(eof-object? (read in))", "#t");
+
+        assert_eval!(
+            env,
+            "(eof-object? (read (open-input-string \" \\n \")))",
+            "#t"
+        );
+        run(
+            "(define in (open-input-string \"1 ; one\\n#| two\\n|# 2 #;3\\n; end\"))",
+            env,
+        )?;
+        assert_eval!(env, "(read in)", "1");
+        assert_eval!(env, "(read in)", "2");
+        assert_eval!(env, "(eof-object? (read in))", "#t");
+        assert!(matches!(
+            run("(read (open-input-string \"(a b\"))", env),
+            Err(Error::UnexpectedEndOfInput)
+        ));
+        assert!(matches!(
+            run("(read (open-input-string \")\"))", env),
+            Err(Error::ParseError(_))
+        ));
+
+        let path = temp_path("read");
+        run(&format!("(define path \"{path}\")"), env)?;
+        run(
+            "(with-output-to-file path (lambda () (write '(name \"oat\")) (write '(version 1))))",
+            env,
+        )?;
+        run("(define config (open-input-file path))", env)?;
+        assert_eval!(env, "(read config)", "(name \"oat\")");
+        assert_eval!(env, "(read config)", "(version 1)");
+        assert_eval!(env, "(eof-object? (read config))", "#t");
+        std::fs::remove_file(path).unwrap();
+
+        Ok(())
+    }
+
+    #[test]
+    fn eof_objects() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(eof-object? (eof-object))", "#t");
+        assert_eval!(env, "(eof-object? '())", "#f");
+        assert_eval!(env, "(eq? (eof-object) (eof-object))", "#t");
+
+        Ok(())
+    }
+}
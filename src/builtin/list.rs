@@ -0,0 +1,456 @@
+use gc::Gc;
+
+use super::{eq, equal, eqv, to_index, to_length, zip_lists, PrimitiveProcedureFunction};
+use crate::environment::Environment;
+use crate::error::{Error, Result, Type};
+use crate::eval::apply;
+use crate::unscheme;
+use crate::value::Value;
+
+pub const BUILTINS: &[(&str, PrimitiveProcedureFunction)] = &[
+    ("pair?", |args, _| {
+        let value = unscheme!(args => &[any])?;
+        Ok(Value::boolean(matches!(*value, Value::Pair(_))))
+    }),
+    ("null?", |args, _| {
+        let value = unscheme!(args => &[any])?;
+        Ok(Value::boolean(matches!(*value, Value::EmptyList)))
+    }),
+    ("list?", |args, _| {
+        let value = unscheme!(args => &[any])?;
+        Ok(Value::boolean(value.iter().all(|e| e.is_ok())))
+    }),
+    ("make-list", |args, _| {
+        let (k, fill) = match args.len() {
+            1 => (unscheme!(args => &[Number])?, Value::boolean(false)),
+            2 => unscheme!(args => &[Number, any])?,
+            n => return Err(Error::arity(2, n)),
+        };
+        Ok(Gc::new(std::iter::repeat_n(fill, to_length(k)?).collect()))
+    }),
+    ("length", |args, _| {
+        let list = unscheme!(args => &[any])?;
+        let length = list.iter().try_fold(0, |n, e| e.map(|_| n + 1))?;
+        Ok(Value::number(length as f64))
+    }),
+    ("append", |args, _| {
+        let lists = args;
+
+        let Some((last, init)) = lists.split_last() else {
+            return Ok(Value::empty_list());
+        };
+
+        let elements = init
+            .iter()
+            .flat_map(|list| list.iter())
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(elements
+            .iter()
+            .rev()
+            .fold(last.clone(), |acc, e| Value::pair(e, &acc)))
+    }),
+    ("reverse", |args, _| {
+        let list = unscheme!(args => &[any])?;
+        list.iter()
+            .try_fold(Value::empty_list(), |acc, e| Ok(Value::pair(&e?, &acc)))
+    }),
+    ("list-tail", |args, _| {
+        let (list, k) = unscheme!(args => &[any, Number])?;
+        list_tail(&list, to_index(k)?)
+    }),
+    ("list-ref", |args, _| {
+        let (list, k) = unscheme!(args => &[any, Number])?;
+        let k = to_index(k)?;
+        match *list_tail(&list, k)? {
+            Value::Pair(ref pair) => Ok(pair.borrow().0.clone()),
+            _ => Err(Error::IndexOutOfBounds(k)),
+        }
+    }),
+    ("list-set!", |args, _| {
+        let (list, (k, value)) = unscheme!(args => &[any, Number, any])?;
+        let k = to_index(k)?;
+        match *list_tail(&list, k)? {
+            Value::Pair(ref pair) => pair.borrow_mut().0 = value,
+            _ => return Err(Error::IndexOutOfBounds(k)),
+        }
+        Ok(Value::void())
+    }),
+    ("list-copy", |args, _| {
+        let list = unscheme!(args => &[any])?;
+
+        let mut elements = Vec::new();
+        let mut tail = list.clone();
+        for pair in pairs(&list) {
+            let Ok(pair) = pair else { break };
+            let (car, cdr) = unscheme!(&pair => Pair)?;
+            elements.push(car);
+            tail = cdr;
+        }
+
+        Ok(elements
+            .iter()
+            .rev()
+            .fold(tail, |acc, e| Value::pair(e, &acc)))
+    }),
+    ("memq", |args, _| {
+        let (obj, list) = unscheme!(args => &[any, any])?;
+        member(&obj, &list, |x, y| Ok(eq(x, y)))
+    }),
+    ("memv", |args, _| {
+        let (obj, list) = unscheme!(args => &[any, any])?;
+        member(&obj, &list, |x, y| Ok(eqv(x, y)))
+    }),
+    ("member", |args, env| match args.len() {
+        2 => {
+            let (obj, list) = unscheme!(args => &[any, any])?;
+            member(&obj, &list, |x, y| Ok(equal(x, y)))
+        }
+        3 => {
+            let (obj, (list, compare)) = unscheme!(args => &[any, any, any])?;
+            member(&obj, &list, |x, y| {
+                Ok(apply(&compare, vec![x.clone(), y.clone()], env)?.to_bool())
+            })
+        }
+        n => Err(Error::arity(3, n)),
+    }),
+    ("assq", |args, _| {
+        let (obj, alist) = unscheme!(args => &[any, any])?;
+        assoc(&obj, &alist, |x, y| Ok(eq(x, y)))
+    }),
+    ("assv", |args, _| {
+        let (obj, alist) = unscheme!(args => &[any, any])?;
+        assoc(&obj, &alist, |x, y| Ok(eqv(x, y)))
+    }),
+    ("assoc", |args, env| match args.len() {
+        2 => {
+            let (obj, alist) = unscheme!(args => &[any, any])?;
+            assoc(&obj, &alist, |x, y| Ok(equal(x, y)))
+        }
+        3 => {
+            let (obj, (alist, compare)) = unscheme!(args => &[any, any, any])?;
+            assoc(&obj, &alist, |x, y| {
+                Ok(apply(&compare, vec![x.clone(), y.clone()], env)?.to_bool())
+            })
+        }
+        n => Err(Error::arity(3, n)),
+    }),
+    ("filter", |args, env| {
+        let (pred, list) = unscheme!(args => &[any, any])?;
+        filter(&pred, &list, true, env)
+    }),
+    ("remove", |args, env| {
+        let (pred, list) = unscheme!(args => &[any, any])?;
+        filter(&pred, &list, false, env)
+    }),
+    ("fold", |args, env| {
+        let (kons, (knil, lists)) = unscheme!(args => &[any, any, rest])?;
+
+        if lists.is_empty() {
+            return Err(Error::arity(3, 2));
+        }
+
+        let ret = zip_lists(lists).try_fold(knil, |acc, args| {
+            let mut args = args?;
+            args.push(acc);
+            apply(&kons, args, env)
+        });
+        ret
+    }),
+    ("fold-left", |args, env| {
+        let (combine, (nil, lists)) = unscheme!(args => &[any, any, rest])?;
+
+        if lists.is_empty() {
+            return Err(Error::arity(3, 2));
+        }
+
+        let ret = zip_lists(lists).try_fold(nil, |acc, args| {
+            let args = std::iter::once(acc).chain(args?).collect();
+            apply(&combine, args, env)
+        });
+        ret
+    }),
+    ("fold-right", |args, env| {
+        let (combine, (nil, lists)) = unscheme!(args => &[any, any, rest])?;
+
+        if lists.is_empty() {
+            return Err(Error::arity(3, 2));
+        }
+
+        let rows = zip_lists(lists).collect::<Result<Vec<_>>>()?;
+
+        rows.into_iter().rev().try_fold(nil, |acc, mut args| {
+            args.push(acc);
+            apply(&combine, args, env)
+        })
+    }),
+    ("reduce", |args, env| {
+        let (f, (ridentity, list)) = unscheme!(args => &[any, any, any])?;
+        let mut elements = list.iter();
+
+        let Some(first) = elements.next() else {
+            return Ok(ridentity);
+        };
+
+        elements.try_fold(first?, |acc, e| apply(&f, vec![e?, acc], env))
+    }),
+    ("reduce-right", |args, env| {
+        let (f, (ridentity, list)) = unscheme!(args => &[any, any, any])?;
+        let mut elements = list.iter().collect::<Result<Vec<_>>>()?.into_iter().rev();
+
+        let Some(last) = elements.next() else {
+            return Ok(ridentity);
+        };
+
+        elements.try_fold(last, |acc, e| apply(&f, vec![e, acc], env))
+    }),
+    ("set-car!", |args, _| {
+        let (pair, car) = unscheme!(args => &[any, any])?;
+        match *pair {
+            Value::Pair(ref cell) => cell.borrow_mut().0 = car,
+            _ => return Err(Error::TypeMismatch(Type::Pair, pair.clone())),
+        }
+        Ok(Value::void())
+    }),
+    ("set-cdr!", |args, _| {
+        let (pair, cdr) = unscheme!(args => &[any, any])?;
+        match *pair {
+            Value::Pair(ref cell) => cell.borrow_mut().1 = cdr,
+            _ => return Err(Error::TypeMismatch(Type::Pair, pair.clone())),
+        }
+        Ok(Value::void())
+    }),
+    ("caar", |args, _| cxr("aa", args)),
+    ("cadr", |args, _| cxr("ad", args)),
+    ("cdar", |args, _| cxr("da", args)),
+    ("cddr", |args, _| cxr("dd", args)),
+    ("caaar", |args, _| cxr("aaa", args)),
+    ("caadr", |args, _| cxr("aad", args)),
+    ("cadar", |args, _| cxr("ada", args)),
+    ("caddr", |args, _| cxr("add", args)),
+    ("cdaar", |args, _| cxr("daa", args)),
+    ("cdadr", |args, _| cxr("dad", args)),
+    ("cddar", |args, _| cxr("dda", args)),
+    ("cdddr", |args, _| cxr("ddd", args)),
+    ("caaaar", |args, _| cxr("aaaa", args)),
+    ("caaadr", |args, _| cxr("aaad", args)),
+    ("caadar", |args, _| cxr("aada", args)),
+    ("caaddr", |args, _| cxr("aadd", args)),
+    ("cadaar", |args, _| cxr("adaa", args)),
+    ("cadadr", |args, _| cxr("adad", args)),
+    ("caddar", |args, _| cxr("adda", args)),
+    ("cadddr", |args, _| cxr("addd", args)),
+    ("cdaaar", |args, _| cxr("daaa", args)),
+    ("cdaadr", |args, _| cxr("daad", args)),
+    ("cdadar", |args, _| cxr("dada", args)),
+    ("cdaddr", |args, _| cxr("dadd", args)),
+    ("cddaar", |args, _| cxr("ddaa", args)),
+    ("cddadr", |args, _| cxr("ddad", args)),
+    ("cdddar", |args, _| cxr("ddda", args)),
+    ("cddddr", |args, _| cxr("dddd", args)),
+];
+
+/// Applies a composition of `car` and `cdr`, where `path` is the part of the name between the
+/// `c` and the `r`, e.g. `"ad"` for `cadr`.
+fn cxr(path: &str, args: &[Gc<Value>]) -> Result<Gc<Value>> {
+    let value = unscheme!(args => &[any])?;
+
+    path.chars().rev().try_fold(value, |value, c| {
+        let (car, cdr) = unscheme!(&value => Pair)?;
+        Ok(if c == 'a' { car } else { cdr })
+    })
+}
+
+/// Iterates over each pair making up `list`, i.e. each non-empty tail of the list.
+fn pairs(list: &Gc<Value>) -> impl Iterator<Item = Result<Gc<Value>>> + '_ {
+    let mut tail = list.clone();
+
+    list.iter().map(move |element| {
+        element?;
+        let (_, cdr) = unscheme!(&tail => Pair)?;
+        Ok(std::mem::replace(&mut tail, cdr))
+    })
+}
+
+fn list_tail(list: &Gc<Value>, k: usize) -> Result<Gc<Value>> {
+    (0..k).try_fold(list.clone(), |tail, _| match *tail {
+        Value::Pair(ref pair) => Ok(pair.borrow().1.clone()),
+        _ => Err(Error::IndexOutOfBounds(k)),
+    })
+}
+
+/// Returns the elements of `list` for which `pred` returns `keep`.
+fn filter(
+    pred: &Gc<Value>,
+    list: &Gc<Value>,
+    keep: bool,
+    env: &mut Environment,
+) -> Result<Gc<Value>> {
+    let mut elements = Vec::new();
+    for element in list.iter() {
+        let element = element?;
+        if apply(pred, vec![element.clone()], env)?.to_bool() == keep {
+            elements.push(element);
+        }
+    }
+
+    Ok(Gc::new(elements.into_iter().collect()))
+}
+
+/// Returns the first tail of `list` whose car is equivalent to `obj`, or `#f`.
+fn member(
+    obj: &Gc<Value>,
+    list: &Gc<Value>,
+    mut compare: impl FnMut(&Gc<Value>, &Gc<Value>) -> Result<bool>,
+) -> Result<Gc<Value>> {
+    for pair in pairs(list) {
+        let pair = pair?;
+        let (car, _) = unscheme!(&pair => Pair)?;
+        if compare(obj, &car)? {
+            return Ok(pair);
+        }
+    }
+
+    Ok(Value::boolean(false))
+}
+
+/// Returns the first pair in `alist` whose car is equivalent to `obj`, or `#f`.
+fn assoc(
+    obj: &Gc<Value>,
+    alist: &Gc<Value>,
+    mut compare: impl FnMut(&Gc<Value>, &Gc<Value>) -> Result<bool>,
+) -> Result<Gc<Value>> {
+    for entry in alist.iter() {
+        let entry = entry?;
+        let (key, _) = unscheme!(&entry => Pair)?;
+        if compare(obj, &key)? {
+            return Ok(entry);
+        }
+    }
+
+    Ok(Value::boolean(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, Result};
+    use crate::{run, unscheme, Environment};
+
+    #[test]
+    fn list_procedures() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(length '(1 2 3))", "3");
+        assert_eval!(env, "(append '(1) '(2 3) '() '(4))", "(1 2 3 4)");
+        assert_eval!(env, "(append)", "()");
+        assert_eval!(env, "(reverse '(1 (2 3) 4))", "(4 (2 3) 1)");
+        assert_eval!(env, "(list-tail '(1 2 3) 1)", "(2 3)");
+        assert_eval!(env, "(list-ref '(1 2 3) 2)", "3");
+        assert_eval!(env, "(memq 'c '(a b c d))", "(c d)");
+        assert_eval!(env, "(member \"b\" '(\"a\" \"b\"))", "(\"b\")");
+        assert_eval!(env, "(memv 5 '(1 2))", "#f");
+        assert_eval!(env, "(assv 2 '((1 one) (2 two)))", "(2 two)");
+        assert_eval!(env, "(assoc 2.0 '((1 one) (2 two)) eqv?)", "(2 two)");
+        assert_eval!(env, "(cadr '(1 2 3))", "2");
+        assert_eval!(env, "(cdddr '(1 2 3 4))", "(4)");
+        assert_eval!(env, "(caadr '(1 (2 3)))", "2");
+
+        Ok(())
+    }
+
+    #[test]
+    fn folds() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(filter pair? '(1 (2) 3 (4)))", "((2) (4))");
+        assert_eval!(env, "(remove pair? '(1 (2) 3 (4)))", "(1 3)");
+        assert_eval!(env, "(fold cons '() '(1 2 3))", "(3 2 1)");
+        assert_eval!(
+            env,
+            "(fold (lambda (x y acc) (cons (list x y) acc)) '() '(a b) '(1 2))",
+            "((b 2) (a 1))"
+        );
+        assert_eval!(env, "(fold-left list '() '(1 2) '(3 4))", "((() 1 3) 2 4)");
+        assert_eval!(env, "(fold-right cons '() '(1 2 3))", "(1 2 3)");
+        assert_eval!(env, "(fold-right list 'z '(1 2) '(3 4))", "(1 3 (2 4 z))");
+        assert_eval!(env, "(reduce + 0 '(1 2 3))", "6");
+        assert_eval!(env, "(reduce + 0 '())", "0");
+        assert_eval!(env, "(reduce-right append '() '((1) (2) (3)))", "(1 2 3)");
+
+        assert!(matches!(
+            run("(length (cons 1 2))", env),
+            Err(Error::ExpectedList(_))
+        ));
+        assert!(matches!(
+            run("(list-ref '(1 2) 2)", env),
+            Err(Error::IndexOutOfBounds(2))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn mutation() -> Result<()> {
+        let env = &mut Environment::default();
+
+        run(
+            "
+            (define a (list 1 2 3))
+            (define b (list-copy a))
+            (set-car! a 0)
+            (list-set! a 2 4)
+            ",
+            env,
+        )?;
+
+        assert_eval!(env, "a", "(0 2 4)");
+        assert_eval!(env, "b", "(1 2 3)");
+
+        run(
+            "
+            (define c (list 1 2))
+            (set-cdr! (cdr c) c)
+            (define d (list 1 2 1 2))
+            (set-cdr! (cdddr d) d)
+            ",
+            env,
+        )?;
+
+        assert_eval!(env, "(list? c)", "#f");
+        assert_eval!(env, "(equal? c d)", "#t");
+        assert!(matches!(
+            run("(length c)", env),
+            Err(Error::ExpectedList(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn iteration() -> Result<()> {
+        let env = &mut Environment::default();
+
+        let list = run("(list 1 2 3)", env)?;
+        let mut sum = 0.0;
+        for element in &*list {
+            let element = element?;
+            sum += unscheme!(&element => Number)?;
+        }
+        assert_eq!(sum, 6.0);
+
+        let improper = run("'(1 2 . 3)", env)?;
+        let elements: Vec<_> = improper.iter().collect();
+        assert!(matches!(
+            elements.as_slice(),
+            [Ok(_), Ok(_), Err(Error::ExpectedList(_))]
+        ));
+
+        let circular = run("(define c (list 1 2)) (set-cdr! (cdr c) c) c", env)?;
+        assert!(matches!(
+            circular.iter().last(),
+            Some(Err(Error::ExpectedList(_)))
+        ));
+
+        Ok(())
+    }
+}
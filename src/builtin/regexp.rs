@@ -0,0 +1,209 @@
+use gc::Gc;
+use regex::{Captures, Regex};
+
+use super::PrimitiveProcedureFunction;
+use crate::environment::Environment;
+use crate::error::{Error, Result, Type};
+use crate::eval::apply;
+use crate::unscheme;
+use crate::value::{Regexp, Value};
+
+pub const BUILTINS: &[(&str, PrimitiveProcedureFunction)] = &[
+    ("regexp", |args, env| {
+        let pattern = unscheme!(args => &[String])?;
+        Ok(Gc::new(Value::Regexp(Regexp(env.regexp(&pattern)?))))
+    }),
+    ("regexp?", |args, _| {
+        let value = unscheme!(args => &[any])?;
+        Ok(Value::boolean(matches!(*value, Value::Regexp(_))))
+    }),
+    ("regexp-match", |args, env| {
+        let (pattern, input) = unscheme!(args => &[any, String])?;
+        let regexp = compile(&pattern, env)?;
+        Ok(match regexp.captures(&input) {
+            Some(captures) => Gc::new(groups(&captures).collect()),
+            None => Value::boolean(false),
+        })
+    }),
+    ("regexp-match?", |args, env| {
+        let (pattern, input) = unscheme!(args => &[any, String])?;
+        let regexp = compile(&pattern, env)?;
+        Ok(Value::boolean(regexp.is_match(&input)))
+    }),
+    ("regexp-match*", |args, env| {
+        let (pattern, input) = unscheme!(args => &[any, String])?;
+        let regexp = compile(&pattern, env)?;
+        let matches = regexp
+            .find_iter(&input)
+            .map(|m| Value::string(m.as_str()))
+            .collect();
+        Ok(Gc::new(matches))
+    }),
+    ("regexp-match-positions", |args, env| {
+        let (pattern, input) = unscheme!(args => &[any, String])?;
+        let regexp = compile(&pattern, env)?;
+
+        let Some(captures) = regexp.captures(&input) else {
+            return Ok(Value::boolean(false));
+        };
+
+        // Positions are character indices, consistent with the string builtins
+        let position = |offset: usize| Value::number(input[..offset].chars().count() as f64);
+        let positions = captures
+            .iter()
+            .map(|group| match group {
+                Some(m) => Value::pair(&position(m.start()), &position(m.end())),
+                None => Value::boolean(false),
+            })
+            .collect();
+
+        Ok(Gc::new(positions))
+    }),
+    ("regexp-replace", |args, env| {
+        let (pattern, (input, insert)) = unscheme!(args => &[any, String, any])?;
+        replace(&pattern, &input, &insert, Some(1), env)
+    }),
+    ("regexp-replace*", |args, env| {
+        let (pattern, (input, insert)) = unscheme!(args => &[any, String, any])?;
+        replace(&pattern, &input, &insert, None, env)
+    }),
+    ("regexp-split", |args, env| {
+        let (pattern, input) = unscheme!(args => &[any, String])?;
+        let regexp = compile(&pattern, env)?;
+        Ok(Gc::new(regexp.split(&input).map(Value::string).collect()))
+    }),
+];
+
+/// Returns the compiled form of `pattern`, which is either a regexp or a string.
+fn compile(pattern: &Gc<Value>, env: &mut Environment) -> Result<Regex> {
+    match &**pattern {
+        Value::Regexp(Regexp(regexp)) => Ok(regexp.clone()),
+        Value::String(s) => env.regexp(&s.borrow()),
+        _ => Err(Error::TypeMismatch(Type::Regexp, pattern.clone())),
+    }
+}
+
+/// The text of each capture group, or `#f` for groups that did not participate in the match.
+fn groups<'a>(captures: &'a Captures) -> impl Iterator<Item = Gc<Value>> + 'a {
+    captures.iter().map(|group| match group {
+        Some(m) => Value::string(m.as_str()),
+        None => Value::boolean(false),
+    })
+}
+
+/// Replaces up to `limit` matches of `pattern` in `input`, or all of them if there is no limit.
+///
+/// `insert` is either a template string, in which `$n` refers to the nth capture group, or a
+/// procedure called with the text of each capture group and returning the replacement.
+fn replace(
+    pattern: &Gc<Value>,
+    input: &str,
+    insert: &Gc<Value>,
+    limit: Option<usize>,
+    env: &mut Environment,
+) -> Result<Gc<Value>> {
+    let regexp = compile(pattern, env)?;
+
+    let mut output = String::new();
+    let mut last = 0;
+
+    for captures in regexp
+        .captures_iter(input)
+        .take(limit.unwrap_or(usize::MAX))
+    {
+        let whole = captures.get_match();
+        output.push_str(&input[last..whole.start()]);
+
+        match &**insert {
+            Value::String(template) => captures.expand(&template.borrow(), &mut output),
+            _ => {
+                let replacement = apply(insert, groups(&captures).collect(), env)?;
+                output.push_str(&unscheme!(&replacement => String)?);
+            }
+        }
+
+        last = whole.end();
+    }
+
+    output.push_str(&input[last..]);
+
+    Ok(Value::string(&output))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, Result};
+    use crate::{run, Environment};
+
+    #[test]
+    fn matching() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(regexp? (regexp \"a+\"))", "#t");
+        assert_eval!(env, "(regexp? \"a+\")", "#f");
+        assert_eval!(
+            env,
+            "(regexp-match \"(\\\\w+)@(\\\\w+)?\" \"λ: fred@\")",
+            "(\"fred@\" \"fred\" #f)"
+        );
+        assert_eval!(env, "(regexp-match (regexp \"b+\") \"abbc\")", "(\"bb\")");
+        assert_eval!(env, "(regexp-match \"x\" \"abc\")", "#f");
+        assert_eval!(env, "(regexp-match? \"^a\" \"abc\")", "#t");
+        assert_eval!(
+            env,
+            "(regexp-match* \"\\\\d+\" \"a1b22c333\")",
+            "(\"1\" \"22\" \"333\")"
+        );
+        assert_eval!(
+            env,
+            "(map car (regexp-match-positions \"λ(b)\" \"aλbc\"))",
+            "(1 2)"
+        );
+        assert_eval!(
+            env,
+            "(map cdr (regexp-match-positions \"λ(b)\" \"aλbc\"))",
+            "(3 3)"
+        );
+
+        assert!(matches!(
+            run("(regexp \"(\")", env),
+            Err(Error::InvalidRegexp(_))
+        ));
+        assert!(matches!(
+            run("(regexp-match 'a \"a\")", env),
+            Err(Error::TypeMismatch(..))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn replacing_and_splitting() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(regexp-replace \"o\" \"foo\" \"0\")", "\"f0o\"");
+        assert_eval!(env, "(regexp-replace* \"o\" \"foo\" \"0\")", "\"f00\"");
+        assert_eval!(
+            env,
+            "(regexp-replace* \"(\\\\w+)=(\\\\w+)\" \"a=1 b=2\" \"$2=$1\")",
+            "\"1=a 2=b\""
+        );
+        assert_eval!(
+            env,
+            "(regexp-replace* \"\\\\d\" \"a1b2\" (lambda (d) (string-append d d)))",
+            "\"a11b22\""
+        );
+        assert_eval!(
+            env,
+            "(regexp-split \",\\\\s*\" \"a, b,c\")",
+            "(\"a\" \"b\" \"c\")"
+        );
+
+        assert!(matches!(
+            run("(regexp-replace \"a\" \"a\" (lambda (m) 1))", env),
+            Err(Error::TypeMismatch(..))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+use gc::Gc;
+
+use super::PrimitiveProcedureFunction;
+use crate::environment::Environment;
+use crate::error::Result;
+use crate::eval::apply;
+use crate::unscheme;
+use crate::value::Value;
+
+pub const BUILTINS: &[(&str, PrimitiveProcedureFunction)] = &[
+    ("sort", |args, env| {
+        let (sequence, less) = unscheme!(args => &[any, any])?;
+        match &*sequence {
+            Value::Vector(vector) => {
+                let elements = vector.borrow().clone();
+                Ok(Value::vector(sort(elements, &less, env)?))
+            }
+            _ => sort_list(&sequence, &less, env),
+        }
+    }),
+    ("list-sort", |args, env| {
+        let (less, list) = unscheme!(args => &[any, any])?;
+        sort_list(&list, &less, env)
+    }),
+    ("vector-sort!", |args, env| {
+        let (vector, less) = unscheme!(args => &[Vector, any])?;
+
+        // Sort a copy, as the comparator is free to access the vector while it is being sorted
+        let elements = vector.borrow().clone();
+        let sorted = sort(elements, &less, env)?;
+        *vector.borrow_mut() = sorted;
+
+        Ok(Value::void())
+    }),
+    ("merge", |args, env| {
+        let (left, (right, less)) = unscheme!(args => &[any, any, any])?;
+        let left = left.iter().collect::<Result<_>>()?;
+        let right = right.iter().collect::<Result<_>>()?;
+        let merged = merge(left, right, &mut comparator(&less, env))?;
+        Ok(Gc::new(merged.into_iter().collect()))
+    }),
+];
+
+fn sort_list(list: &Gc<Value>, less: &Gc<Value>, env: &mut Environment) -> Result<Gc<Value>> {
+    let elements = list.iter().collect::<Result<_>>()?;
+    Ok(Gc::new(sort(elements, less, env)?.into_iter().collect()))
+}
+
+/// Stably sorts `elements` using the Scheme procedure `less` as the comparator.
+fn sort(
+    elements: Vec<Gc<Value>>,
+    less: &Gc<Value>,
+    env: &mut Environment,
+) -> Result<Vec<Gc<Value>>> {
+    merge_sort(elements, &mut comparator(less, env))
+}
+
+/// Wraps the Scheme procedure `less` so that it can be called from Rust.
+fn comparator<'a>(
+    less: &'a Gc<Value>,
+    env: &'a mut Environment,
+) -> impl FnMut(&Gc<Value>, &Gc<Value>) -> Result<bool> + 'a {
+    |a, b| Ok(apply(less, vec![a.clone(), b.clone()], env)?.to_bool())
+}
+
+/// A stable merge sort, where `less` returns whether its first argument must come before its
+/// second. Stops at the first error returned by `less`.
+fn merge_sort(
+    mut elements: Vec<Gc<Value>>,
+    less: &mut impl FnMut(&Gc<Value>, &Gc<Value>) -> Result<bool>,
+) -> Result<Vec<Gc<Value>>> {
+    if elements.len() <= 1 {
+        return Ok(elements);
+    }
+
+    let right = elements.split_off(elements.len() / 2);
+    let left = merge_sort(elements, less)?;
+    let right = merge_sort(right, less)?;
+
+    merge(left, right, less)
+}
+
+/// Merges two sorted sequences, preferring elements from `left` when they are equivalent.
+fn merge(
+    left: Vec<Gc<Value>>,
+    right: Vec<Gc<Value>>,
+    less: &mut impl FnMut(&Gc<Value>, &Gc<Value>) -> Result<bool>,
+) -> Result<Vec<Gc<Value>>> {
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        // Only take from the right when strictly less, which keeps the sort stable
+        let next = if less(r, l)? { &mut right } else { &mut left };
+        merged.extend(next.next());
+    }
+
+    merged.extend(left);
+    merged.extend(right);
+
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use gc::Gc;
+    use proptest::prelude::*;
+
+    use crate::error::{Error, Result};
+    use crate::{run, Environment, Value};
+
+    /// An entry `(key index)`, where the index records the original position of the key.
+    fn entry(index: usize, key: i8) -> Gc<Value> {
+        Gc::new(
+            [Value::number(key.into()), Value::number(index as f64)]
+                .into_iter()
+                .collect(),
+        )
+    }
+
+    fn entries(keys: &[i8]) -> Vec<Gc<Value>> {
+        keys.iter()
+            .enumerate()
+            .map(|(i, &key)| entry(i, key))
+            .collect()
+    }
+
+    /// Sorts the entries by key using Rust's stable sort, for comparison.
+    fn expected(keys: &[i8]) -> Vec<Gc<Value>> {
+        let mut indexed: Vec<_> = keys.iter().copied().enumerate().collect();
+        indexed.sort_by_key(|&(_, key)| key);
+        indexed.into_iter().map(|(i, key)| entry(i, key)).collect()
+    }
+
+    fn sorted(env: &mut Environment, input: &str) -> Result<Vec<Gc<Value>>> {
+        let sorted = run(input, env)?;
+        match &*sorted {
+            Value::Vector(vector) => Ok(vector.borrow().clone()),
+            _ => sorted.iter().collect(),
+        }
+    }
+
+    fn env_with(keys: &[i8]) -> Environment {
+        let mut env = Environment::default();
+        env.bind("entries", Gc::new(entries(keys).into_iter().collect()));
+        run("(define (less? a b) (< (car a) (car b)))", &mut env).unwrap();
+        env
+    }
+
+    proptest! {
+        #[test]
+        fn sort_matches_sort_by(keys: Vec<i8>) {
+            let env = &mut env_with(&keys);
+            let expected = expected(&keys);
+
+            prop_assert_eq!(&sorted(env, "(sort entries less?)").unwrap(), &expected);
+            prop_assert_eq!(&sorted(env, "(list-sort less? entries)").unwrap(), &expected);
+            prop_assert_eq!(
+                &sorted(env, "(sort (list->vector entries) less?)").unwrap(),
+                &expected
+            );
+
+            run("(define v (list->vector entries))", env).unwrap();
+            run("(vector-sort! v less?)", env).unwrap();
+            prop_assert_eq!(&sorted(env, "v").unwrap(), &expected);
+        }
+
+        #[test]
+        fn merge_matches_sort_by(mut left: Vec<i8>, mut right: Vec<i8>) {
+            left.sort();
+            right.sort();
+            let keys: Vec<_> = left.iter().chain(&right).copied().collect();
+
+            let env = &mut env_with(&keys);
+            let mut entries = entries(&keys);
+            let right = entries.split_off(left.len());
+            env.bind("left", Gc::new(entries.into_iter().collect()));
+            env.bind("right", Gc::new(right.into_iter().collect()));
+
+            prop_assert_eq!(
+                &sorted(env, "(merge left right less?)").unwrap(),
+                &expected(&keys)
+            );
+        }
+    }
+
+    #[test]
+    fn comparator_errors() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(sort '() car)", "()");
+        assert_eval!(env, "(sort '(3 1 2) >)", "(3 2 1)");
+
+        assert!(matches!(
+            run("(sort '(1 a 2) <)", env),
+            Err(Error::TypeMismatch(..))
+        ));
+        assert!(matches!(
+            run("(list-sort (lambda (a b) (undefined a b)) '(1 2))", env)
+                .as_ref()
+                .map_err(Error::root),
+            Err(Error::UndefinedVariable(_))
+        ));
+
+        run("(define v (vector 2 1))", env)?;
+        assert!(run("(vector-sort! v car)", env).is_err());
+        assert_eval!(env, "v", "#(2 1)");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+use std::ops::Range;
+
+use gc::{Gc, GcCell};
+
+use super::{to_index, to_length, PrimitiveProcedureFunction};
+use crate::environment::Environment;
+use crate::error::{Error, Result, Type};
+use crate::eval::apply;
+use crate::unscheme;
+use crate::value::Value;
+
+pub const BUILTINS: &[(&str, PrimitiveProcedureFunction)] = &[
+    ("string?", |args, _| {
+        let value = unscheme!(args => &[any])?;
+        Ok(Value::boolean(matches!(*value, Value::String(_))))
+    }),
+    ("string-length", |args, _| {
+        let string = unscheme!(args => &[String])?;
+        Ok(Value::number(string.chars().count() as f64))
+    }),
+    ("string-ref", |args, _| {
+        let (string, k) = unscheme!(args => &[String, Number])?;
+        let k = to_index(k)?;
+        let c = string.chars().nth(k);
+        Ok(Value::character(c.ok_or(Error::IndexOutOfBounds(k))?))
+    }),
+    ("substring", |args, _| {
+        let (string, (start, end)) = unscheme!(args => &[String, Number, Number])?;
+        let range = char_range(&string, to_index(start)?, to_index(end)?)?;
+        Ok(Value::string(&string[range]))
+    }),
+    ("string-append", |args, _| {
+        let string = args
+            .iter()
+            .map(|p| unscheme!(p => String))
+            .collect::<Result<String>>()?;
+        Ok(Value::string(&string))
+    }),
+    ("string=?", |args, _| compare(args, false, |a, b| a == b)),
+    ("string<?", |args, _| compare(args, false, |a, b| a < b)),
+    ("string>?", |args, _| compare(args, false, |a, b| a > b)),
+    ("string<=?", |args, _| compare(args, false, |a, b| a <= b)),
+    ("string>=?", |args, _| compare(args, false, |a, b| a >= b)),
+    ("string-ci=?", |args, _| compare(args, true, |a, b| a == b)),
+    ("string-ci<?", |args, _| compare(args, true, |a, b| a < b)),
+    ("string-ci>?", |args, _| compare(args, true, |a, b| a > b)),
+    ("string-ci<=?", |args, _| compare(args, true, |a, b| a <= b)),
+    ("string-ci>=?", |args, _| compare(args, true, |a, b| a >= b)),
+    ("string-upcase", |args, _| {
+        let string = unscheme!(args => &[String])?;
+        Ok(Value::string(&string.to_uppercase()))
+    }),
+    ("string-downcase", |args, _| {
+        let string = unscheme!(args => &[String])?;
+        Ok(Value::string(&string.to_lowercase()))
+    }),
+    ("string-foldcase", |args, _| {
+        let string = unscheme!(args => &[String])?;
+        Ok(Value::string(&foldcase(&string)))
+    }),
+    ("make-string", |args, _| {
+        let (k, fill) = match args.len() {
+            1 => (unscheme!(args => &[Number])?, ' '),
+            2 => unscheme!(args => &[Number, Character])?,
+            n => return Err(Error::arity(2, n)),
+        };
+        Ok(Value::string(&String::from_iter(std::iter::repeat_n(
+            fill,
+            to_length(k)?,
+        ))))
+    }),
+    ("string-copy", |args, _| {
+        let (string, bounds) = unscheme!(args => &[String, rest])?;
+        let range = byte_range(&string, bounds)?;
+        Ok(Value::string(&string[range]))
+    }),
+    ("string->list", |args, _| {
+        let (string, bounds) = unscheme!(args => &[String, rest])?;
+        let range = byte_range(&string, bounds)?;
+        Ok(Gc::new(
+            string[range].chars().map(Value::character).collect(),
+        ))
+    }),
+    ("list->string", |args, _| {
+        let list = unscheme!(args => &[any])?;
+        let string = list
+            .iter()
+            .map(|c| c.and_then(|c| unscheme!(&c => Character)))
+            .collect::<Result<String>>()?;
+        Ok(Value::string(&string))
+    }),
+    ("string->symbol", |args, _| {
+        let string = unscheme!(args => &[String])?;
+        Ok(Value::symbol(&string))
+    }),
+    ("symbol->string", |args, _| {
+        let symbol = unscheme!(args => &[Symbol])?;
+        Ok(Value::string(&symbol))
+    }),
+    ("string-set!", |args, _| {
+        let (string, (k, c)) = unscheme!(args => &[any, Number, Character])?;
+        let cell = string_cell(&string)?;
+        let mut string = cell.borrow_mut();
+
+        let k = to_index(k)?;
+        let (offset, old) = string
+            .char_indices()
+            .nth(k)
+            .ok_or(Error::IndexOutOfBounds(k))?;
+        string.replace_range(offset..offset + old.len_utf8(), c.encode_utf8(&mut [0; 4]));
+
+        Ok(Value::void())
+    }),
+    ("string-fill!", |args, _| {
+        let (string, (c, bounds)) = unscheme!(args => &[any, Character, rest])?;
+        let cell = string_cell(&string)?;
+        let mut string = cell.borrow_mut();
+
+        let range = byte_range(&string, bounds)?;
+        let filled = String::from_iter(std::iter::repeat_n(
+            c,
+            string[range.clone()].chars().count(),
+        ));
+        string.replace_range(range, &filled);
+
+        Ok(Value::void())
+    }),
+    ("string-index", |args, env| {
+        let (string, pred) = unscheme!(args => &[String, any])?;
+        for (i, c) in string.chars().enumerate() {
+            if matches_char(&pred, c, env)? {
+                return Ok(Value::number(i as f64));
+            }
+        }
+        Ok(Value::boolean(false))
+    }),
+    ("string-contains", |args, _| {
+        let (string, pattern) = unscheme!(args => &[String, String])?;
+        Ok(match string.find(&pattern) {
+            Some(offset) => Value::number(string[..offset].chars().count() as f64),
+            None => Value::boolean(false),
+        })
+    }),
+    ("string-join", |args, _| {
+        let (list, delimiter) = match args.len() {
+            1 => (unscheme!(args => &[any])?, " ".to_owned()),
+            2 => unscheme!(args => &[any, String])?,
+            n => return Err(Error::arity(2, n)),
+        };
+        let strings = list
+            .iter()
+            .map(|s| s.and_then(|s| unscheme!(&s => String)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Value::string(&strings.join(&delimiter)))
+    }),
+    ("string-split", |args, _| {
+        let (string, delimiter) = match args.len() {
+            1 => (unscheme!(args => &[String])?, None),
+            2 => {
+                let (string, delimiter) = unscheme!(args => &[String, any])?;
+                (string, Some(delimiter))
+            }
+            n => return Err(Error::arity(2, n)),
+        };
+
+        let fields: Vec<_> = match delimiter {
+            None => string.split_whitespace().collect(),
+            Some(delimiter) => match &*delimiter {
+                Value::Character(c) => string.split(*c).collect(),
+                Value::String(s) => string.split(&*s.borrow()).collect(),
+                _ => return Err(Error::TypeMismatch(Type::String, delimiter.clone())),
+            },
+        };
+
+        Ok(Gc::new(fields.into_iter().map(Value::string).collect()))
+    }),
+    ("string-trim", |args, _| {
+        let string = unscheme!(args => &[String])?;
+        Ok(Value::string(string.trim()))
+    }),
+];
+
+/// Case folds `s` for case-insensitive comparisons, as R7RS requires of `string-foldcase`.
+///
+/// Full case folding is approximated by converting each character to uppercase and then to
+/// lowercase, so that characters which fold to several, such as `ß` to `ss`, are expanded. Unlike
+/// `string-downcase`, a final `Σ` folds to `σ` rather than `ς`.
+fn foldcase(s: &str) -> String {
+    s.chars()
+        .flat_map(char::to_uppercase)
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Returns the mutable contents of a string value.
+fn string_cell(value: &Gc<Value>) -> Result<&GcCell<String>> {
+    match &**value {
+        Value::String(s) => Ok(s),
+        _ => Err(Error::TypeMismatch(Type::String, value.clone())),
+    }
+}
+
+/// Converts the optional `start` and `end` character indices in `bounds`, which default to the
+/// whole of `string`, to a range of byte offsets into `string`.
+fn byte_range(string: &str, bounds: &[Gc<Value>]) -> Result<Range<usize>> {
+    let bounds = bounds
+        .iter()
+        .map(|bound| unscheme!(bound => Number))
+        .collect::<Result<Vec<_>>>()?;
+
+    let len = string.chars().count();
+    let (start, end) = match bounds[..] {
+        [] => (0, len),
+        [start] => (to_index(start)?, len),
+        [start, end] => (to_index(start)?, to_index(end)?),
+        _ => return Err(Error::arity(3, bounds.len() + 1)),
+    };
+
+    char_range(string, start, end)
+}
+
+/// Converts the character indices `start..end` to a range of byte offsets into `string`,
+/// checking that they lie within the string.
+fn char_range(string: &str, start: usize, end: usize) -> Result<Range<usize>> {
+    let mut offsets = string
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain([string.len()]);
+
+    let start_offset = offsets.nth(start).ok_or(Error::IndexOutOfBounds(start))?;
+    let end_offset = match end.checked_sub(start) {
+        Some(0) => start_offset,
+        Some(n) => offsets.nth(n - 1).ok_or(Error::IndexOutOfBounds(end))?,
+        None => return Err(Error::IndexOutOfBounds(start)),
+    };
+
+    Ok(start_offset..end_offset)
+}
+
+/// Tests `c` against `pred`, which is either a character to compare with or a predicate.
+fn matches_char(pred: &Gc<Value>, c: char, env: &mut Environment) -> Result<bool> {
+    match **pred {
+        Value::Character(expected) => Ok(c == expected),
+        _ => Ok(apply(pred, vec![Value::character(c)], env)?.to_bool()),
+    }
+}
+
+/// Checks that `cmp` holds between each adjacent pair of strings in `args`, comparing them
+/// case-insensitively if `fold` is set.
+fn compare(args: &[Gc<Value>], fold: bool, cmp: fn(&str, &str) -> bool) -> Result<Gc<Value>> {
+    let strings = args
+        .iter()
+        .map(|p| unscheme!(p => String))
+        .map(|s| s.map(|s| if fold { foldcase(&s) } else { s }))
+        .collect::<Result<Vec<_>>>()?;
+
+    if strings.is_empty() {
+        return Err(Error::arity(1, 0));
+    }
+
+    Ok(Value::boolean(
+        strings.windows(2).all(|w| cmp(&w[0], &w[1])),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, Result};
+    use crate::{run, Environment};
+
+    #[test]
+    fn comparisons() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(string=? \"abc\" \"abc\" \"abc\")", "#t");
+        assert_eval!(env, "(string=? \"abc\" \"ABC\")", "#f");
+        assert_eval!(env, "(string<? \"abc\" \"abd\" \"b\")", "#t");
+        assert_eval!(env, "(string<? \"b\" \"abc\")", "#f");
+        assert_eval!(env, "(string>=? \"b\" \"b\" \"a\")", "#t");
+        assert_eval!(env, "(string-ci=? \"Straße\" \"STRAßE\")", "#t");
+        assert_eval!(env, "(string-ci<? \"abc\" \"ABD\")", "#t");
+        assert_eval!(env, "(string-ci=? \"STRASSE\" \"straße\")", "#t");
+        assert_eval!(env, "(string-ci=? \"ΣΑΣ\" \"σας\")", "#t");
+        assert_eval!(env, "(string-ci<? \"straße\" \"STRASSF\")", "#t");
+
+        Ok(())
+    }
+
+    #[test]
+    fn conversions() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(string-upcase \"straße\")", "\"STRASSE\"");
+        assert_eval!(env, "(string-downcase \"ΣΑΣ\")", "\"σας\"");
+        assert_eval!(env, "(string-foldcase \"Straße ΣΑΣ\")", "\"strasse σασ\"");
+        assert_eval!(env, "(string->list \"aλb\")", "(#\\a #\\λ #\\b)");
+        assert_eval!(env, "(string->list \"aλbc\" 1 3)", "(#\\λ #\\b)");
+        assert_eval!(env, "(list->string '(#\\a #\\λ))", "\"aλ\"");
+        assert_eval!(env, "(string->symbol \"a b\")", "|a b|");
+        assert_eval!(env, "(symbol->string 'abc)", "\"abc\"");
+        assert_eval!(env, "(make-string 3 #\\λ)", "\"λλλ\"");
+        assert_eval!(env, "(string-length (make-string 16777216))", "16777216");
+        assert!(matches!(
+            run("(make-string 16777217)", env),
+            Err(Error::LengthTooLarge { .. })
+        ));
+        assert_eval!(env, "(string-copy \"λabc\" 1)", "\"abc\"");
+        assert!(matches!(
+            run("(string-copy \"abc\" 2 4)", env),
+            Err(Error::IndexOutOfBounds(4))
+        ));
+        assert!(matches!(
+            run("(string-copy \"abc\" 2 1)", env),
+            Err(Error::IndexOutOfBounds(2))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn mutation() -> Result<()> {
+        let env = &mut Environment::default();
+
+        run("(define s (make-string 3 #\\a))", env)?;
+        run("(define t s)", env)?;
+        run("(string-set! s 1 #\\λ)", env)?;
+        assert_eval!(env, "t", "\"aλa\"");
+        run("(string-fill! s #\\z 2)", env)?;
+        assert_eval!(env, "t", "\"aλz\"");
+        run("(string-fill! s #\\b)", env)?;
+        assert_eval!(env, "t", "\"bbb\"");
+
+        run("(define u (string-copy s))", env)?;
+        run("(string-set! u 0 #\\c)", env)?;
+        assert_eval!(env, "s", "\"bbb\"");
+
+        assert!(matches!(
+            run("(string-set! s 3 #\\a)", env),
+            Err(Error::IndexOutOfBounds(3))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn searching() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(string-index \"λabc\" #\\b)", "2");
+        assert_eval!(env, "(string-index \"λabc\" char-numeric?)", "#f");
+        assert_eval!(env, "(string-contains \"λabcabc\" \"ca\")", "3");
+        assert_eval!(env, "(string-contains \"abc\" \"d\")", "#f");
+        assert_eval!(env, "(string-join '(\"a\" \"b\" \"c\"))", "\"a b c\"");
+        assert_eval!(env, "(string-join '(\"a\" \"b\") \", \")", "\"a, b\"");
+        assert_eval!(env, "(string-split \" a  b \")", "(\"a\" \"b\")");
+        assert_eval!(env, "(string-split \"a,,b\" #\\,)", "(\"a\" \"\" \"b\")");
+        assert_eval!(env, "(string-split \"a, b\" \", \")", "(\"a\" \"b\")");
+        assert_eval!(env, "(string-trim \"  a b \\n\")", "\"a b\"");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use gc::Gc;
+
+use super::{to_index, to_length, PrimitiveProcedureFunction};
+use crate::error::{Error, Result};
+use crate::unscheme;
+use crate::value::Value;
+
+pub const BUILTINS: &[(&str, PrimitiveProcedureFunction)] = &[
+    ("vector?", |args, _| {
+        let value = unscheme!(args => &[any])?;
+        Ok(Value::boolean(matches!(*value, Value::Vector(_))))
+    }),
+    ("make-vector", |args, _| {
+        let (k, fill) = match args.len() {
+            1 => (unscheme!(args => &[Number])?, Value::boolean(false)),
+            2 => unscheme!(args => &[Number, any])?,
+            n => return Err(Error::arity(2, n)),
+        };
+        Ok(Value::vector(vec![fill; to_length(k)?]))
+    }),
+    ("vector", |args, _| Ok(Value::vector(args.to_vec()))),
+    ("vector-length", |args, _| {
+        let vector = unscheme!(args => &[Vector])?;
+        let length = vector.borrow().len();
+        Ok(Value::number(length as f64))
+    }),
+    ("vector-ref", |args, _| {
+        let (vector, k) = unscheme!(args => &[Vector, Number])?;
+        let k = to_index(k)?;
+        let element = vector.borrow().get(k).cloned();
+        element.ok_or(Error::IndexOutOfBounds(k))
+    }),
+    ("vector-set!", |args, _| {
+        let (vector, (k, value)) = unscheme!(args => &[Vector, Number, any])?;
+        let k = to_index(k)?;
+        match vector.borrow_mut().get_mut(k) {
+            Some(element) => *element = value,
+            None => return Err(Error::IndexOutOfBounds(k)),
+        }
+        Ok(Value::void())
+    }),
+    ("vector->list", |args, _| {
+        let vector = unscheme!(args => &[Vector])?;
+        let list = vector.borrow().iter().cloned().collect();
+        Ok(Gc::new(list))
+    }),
+    ("list->vector", |args, _| {
+        let list = unscheme!(args => &[any])?;
+        Ok(Value::vector(list.iter().collect::<Result<_>>()?))
+    }),
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, Result};
+    use crate::{run, Environment};
+
+    #[test]
+    fn vectors() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert_eval!(env, "(vector 1 'a \"b\")", "#(1 a \"b\")");
+        assert_eval!(env, "(make-vector 2 'x)", "#(x x)");
+        assert_eval!(env, "(vector-length #(1 2 3))", "3");
+        assert_eval!(env, "(vector-ref #(1 2 3) 1)", "2");
+        assert_eval!(env, "(vector->list #(1 2))", "(1 2)");
+        assert_eval!(env, "(list->vector '(1 2))", "#(1 2)");
+        assert_eval!(env, "(vector? #())", "#t");
+        assert_eval!(env, "(vector? '())", "#f");
+
+        run("(define v (vector 1 2 3))", env)?;
+        run("(define w v)", env)?;
+        run("(vector-set! v 0 'a)", env)?;
+        assert_eval!(env, "w", "#(a 2 3)");
+
+        assert!(matches!(
+            run("(vector-ref v 3)", env),
+            Err(Error::IndexOutOfBounds(3))
+        ));
+        assert!(matches!(
+            run("(make-vector 4294967295)", env),
+            Err(Error::LengthTooLarge {
+                length: 4294967295,
+                ..
+            })
+        ));
+        assert_eq!(
+            run("(make-vector 20000000)", env).unwrap_err().to_string(),
+            "length 20000000 is larger than the maximum of 16777216"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,301 @@
+use std::fmt::{Display, Formatter, Result};
+
+use crate::error::{Error, SyntaxError, SyntaxErrorKind};
+use crate::span::Span;
+
+/// A message about an error, pointing to where in the source it occurred.
+struct Diagnostic {
+    message: String,
+    span: Option<Span>,
+    /// Printed beneath the underlined part of the source.
+    label: Option<String>,
+    help: Option<String>,
+}
+
+/// ANSI escape codes for the parts of a diagnostic.
+const ERROR: &str = "\x1b[1;31m";
+const MARGIN: &str = "\x1b[1;34m";
+const HELP: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// An error rendered for display to a user, as returned by [`Error::report`].
+pub struct Report<'a> {
+    error: &'a Error,
+    color: bool,
+}
+
+impl Error {
+    /// Renders the error for display to a user, quoting the source code at its location, and
+    /// ending with a traceback of the procedure calls it propagated out of. The output is
+    /// colored with ANSI escape codes if `color` is set.
+    pub fn report(&self, color: bool) -> Report<'_> {
+        Report { error: self, color }
+    }
+}
+
+impl Display for Report<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let Self { error, color } = *self;
+
+        match error.root() {
+            Error::ParseError(errors) => {
+                for error in errors {
+                    syntax_error(error).render(f, color)?;
+                }
+            }
+            err => Diagnostic {
+                message: err.to_string(),
+                span: error.span().cloned(),
+                label: None,
+                help: help(err),
+            }
+            .render(f, color)?,
+        }
+
+        if !error.trace().is_empty() {
+            writeln!(f, "traceback (most recent call last):")?;
+        }
+
+        for frame in error.trace().iter().rev() {
+            match frame.elided {
+                0 => {}
+                1 => writeln!(f, "  ... 1 tail call elided")?,
+                n => writeln!(f, "  ... {n} tail calls elided")?,
+            }
+            writeln!(f, "  {frame}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn syntax_error(error: &SyntaxError) -> Diagnostic {
+    let text = error.span.source.text();
+    let before = text[..error.span.range.start].chars().next_back();
+
+    let (label, help) = match &error.kind {
+        SyntaxErrorKind::Unexpected { expected, .. } => {
+            let label = match expected.as_slice() {
+                [] => None,
+                [expected] => Some(format!("expected {expected}")),
+                [init @ .., last] => Some(format!("expected {} or {last}", init.join(", "))),
+            };
+
+            let help = match before {
+                Some('#') => Some(
+                    "`#` begins a boolean (`#t`), a character (`#\\a`), a vector (`#(...)`) or a \
+                     comment",
+                ),
+                Some('\\') => Some(
+                    "the escapes in strings are `\\a`, `\\b`, `\\t`, `\\n`, `\\r`, `\\0`, `\\\"`, \
+                     `\\\\` and `\\x<hex>;`",
+                ),
+                _ => None,
+            };
+
+            (label, help)
+        }
+        SyntaxErrorKind::Unclosed(delimiter) => (
+            Some(format!("this `{delimiter}` is never closed")),
+            Some(match *delimiter {
+                "\"" => "add a `\"` to end the string",
+                "|" => "add a `|` to end the symbol",
+                "#|" => "add a `|#` to end the comment",
+                _ => "add a `)` where the list should end",
+            }),
+        ),
+        SyntaxErrorKind::Unmatched => (
+            Some("this `)` has no matching `(`".to_owned()),
+            Some("remove it, or add a `(` where the list should begin"),
+        ),
+        SyntaxErrorKind::Invalid(_) => (None, None),
+    };
+
+    // Expectations go in the label rather than the message
+    let message = match error.kind {
+        SyntaxErrorKind::Unexpected {
+            found: Some(found), ..
+        } => format!("unexpected {found:?}"),
+        SyntaxErrorKind::Unexpected { found: None, .. } => "unexpected end of input".to_owned(),
+        _ => error.to_string(),
+    };
+
+    Diagnostic {
+        message,
+        span: Some(error.span.clone()),
+        label,
+        help: help.map(str::to_owned),
+    }
+}
+
+/// Suggests how to fix common mistakes.
+fn help(err: &Error) -> Option<String> {
+    match err {
+        Error::EmptyApplication => Some("to write the empty list, quote it: `'()`".to_owned()),
+        Error::ExpectedProcedure(_) => {
+            Some("to write a list of data rather than a call, quote it: `'(1 2 3)`".to_owned())
+        }
+        Error::ExpectedValue(_) => {
+            Some("definitions can't be used as values, e.g. as arguments".to_owned())
+        }
+        Error::UnexpectedEndOfInput => Some("check for an unclosed `(`, `\"` or `#|`".to_owned()),
+        _ => None,
+    }
+}
+
+impl Diagnostic {
+    fn render(&self, f: &mut Formatter<'_>, color: bool) -> Result {
+        let paint = |style: &str, text: &str| match color {
+            true => format!("{style}{text}{RESET}"),
+            false => text.to_owned(),
+        };
+
+        writeln!(
+            f,
+            "{}{}",
+            paint(ERROR, "error"),
+            paint(BOLD, &format!(": {}", self.message)),
+        )?;
+
+        let Some(span) = &self.span else {
+            if let Some(help) = &self.help {
+                writeln!(f, "{}: {help}", paint(HELP, "help"))?;
+            }
+            return Ok(());
+        };
+
+        let (line, column) = span.source.location(span.range.start);
+        let text = span.source.text();
+        let source_line = text.lines().nth(line - 1).unwrap_or_default();
+
+        // Only the first line of a span that covers several is underlined
+        let width = text[span.range.clone()]
+            .lines()
+            .next()
+            .map_or(0, |s| s.chars().count())
+            .min(source_line.chars().count().saturating_sub(column - 1))
+            .max(1);
+
+        // Tabs are kept so that the underline lines up however they are displayed
+        let indent: String = source_line
+            .chars()
+            .take(column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        let number = line.to_string();
+        let margin = " ".repeat(number.len());
+        let bar = paint(MARGIN, "|");
+
+        writeln!(f, "{margin}{} {span}", paint(MARGIN, "-->"))?;
+        writeln!(f, "{margin} {bar}")?;
+        writeln!(f, "{} {bar} {source_line}", paint(MARGIN, &number))?;
+        write!(
+            f,
+            "{margin} {bar} {indent}{}",
+            paint(ERROR, &"^".repeat(width))
+        )?;
+        match &self.label {
+            Some(label) => writeln!(f, " {}", paint(ERROR, label))?,
+            None => writeln!(f)?,
+        }
+
+        if let Some(help) = &self.help {
+            writeln!(
+                f,
+                "{margin} {} {}: {help}",
+                paint(MARGIN, "="),
+                paint(HELP, "help"),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::{eval, Environment};
+
+    fn report(input: &str) -> String {
+        let env = &mut Environment::default();
+        let mut err = None;
+
+        match env.parse_source("test.scm", input) {
+            Ok(values) => {
+                for value in values {
+                    if let Err(e) = eval(value, env) {
+                        err = Some(e);
+                        break;
+                    }
+                }
+            }
+            Err(e) => err = Some(e),
+        }
+
+        err.expect("expected an error").report(false).to_string()
+    }
+
+    #[test]
+    fn runtime_errors() {
+        assert_eq!(
+            report("(define (f x)\n  (car x))\n(f 1)"),
+            "\
+error: expected an value of type 'pair', but found `1`
+ --> test.scm:2:3
+  |
+2 |   (car x))
+  |   ^^^^^^^
+traceback (most recent call last):
+  test.scm:3:1: in `f`
+"
+        );
+
+        assert_eq!(
+            report("(1 2 3)"),
+            "\
+error: expected a procedure in application but found `1`
+ --> test.scm:1:1
+  |
+1 | (1 2 3)
+  | ^^^^^^^
+  = help: to write a list of data rather than a call, quote it: `'(1 2 3)`
+"
+        );
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert_eq!(
+            report("(list 1\n\t(a . b c))"),
+            "\
+error: unexpected 'c'
+ --> test.scm:2:9
+  |
+2 | \t(a . b c))
+  | \t       ^ expected ')'
+"
+        );
+
+        assert_eq!(
+            report("(a) #z"),
+            "\
+error: unexpected 'z'
+ --> test.scm:1:6
+  |
+1 | (a) #z
+  |      ^ expected 't', 'f', '\\' or '('
+  = help: `#` begins a boolean (`#t`), a character (`#\\a`), a vector (`#(...)`) or a comment
+"
+        );
+    }
+
+    #[test]
+    fn color() {
+        let report = Error::EmptyApplication.report(true).to_string();
+        assert!(report.starts_with("\x1b[1;31merror\x1b[0m"));
+        assert!(report.contains("\x1b[1;36mhelp\x1b[0m"));
+    }
+}
@@ -1,13 +1,33 @@
 use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::rc::Rc;
 
 use gc::Gc;
 
 use crate::error::{Error, Result};
+use crate::eval::eval;
+use crate::library::Library;
+use crate::parse::parse_spanned;
+use crate::port::Port;
+use crate::span::{Source, Span, SpanTable};
 use crate::value::Value;
 
+/// The maximum number of compiled regular expressions kept by an [`Environment`].
+#[cfg(feature = "regex")]
+const REGEXP_CACHE_SIZE: usize = 64;
+
 #[derive(Debug, Clone)]
 pub struct Environment {
     frames: Vec<HashMap<String, Gc<Value>>>,
+    /// The port read from and written to when none is given explicitly.
+    input: Gc<Port>,
+    output: Gc<Port>,
+    error: Gc<Port>,
+    /// Compiled regular expressions, keyed by their pattern.
+    #[cfg(feature = "regex")]
+    regexps: HashMap<String, regex::Regex>,
+    /// The location of each datum read by [`Environment::parse_source`] that is still alive.
+    spans: SpanTable,
 }
 
 impl Environment {
@@ -39,12 +59,214 @@ impl Environment {
     pub fn restore(&mut self, depth: usize) {
         self.frames.truncate(depth);
     }
+
+    /// Replaces standard input, which is the current input port unless a procedure such as
+    /// `with-input-from-file` has changed it.
+    pub fn set_stdin(&mut self, reader: impl BufRead + 'static) {
+        self.input = Gc::new(Port::input(reader, false));
+    }
+
+    /// Replaces standard output, to which `display` and other output procedures write by
+    /// default, e.g. to capture output in a buffer instead of writing to the process's stdout.
+    pub fn set_stdout(&mut self, writer: impl Write + 'static) {
+        self.output = Gc::new(Port::output(writer, false));
+    }
+
+    /// Replaces standard error, which is returned by `current-error-port`.
+    pub fn set_stderr(&mut self, writer: impl Write + 'static) {
+        self.error = Gc::new(Port::output(writer, false));
+    }
+
+    pub(crate) fn input_port(&self) -> Gc<Port> {
+        self.input.clone()
+    }
+
+    pub(crate) fn output_port(&self) -> Gc<Port> {
+        self.output.clone()
+    }
+
+    pub(crate) fn error_port(&self) -> Gc<Port> {
+        self.error.clone()
+    }
+
+    /// Makes `port` the current input port, returning the previous one.
+    pub(crate) fn replace_input_port(&mut self, port: Gc<Port>) -> Gc<Port> {
+        std::mem::replace(&mut self.input, port)
+    }
+
+    /// Makes `port` the current output port, returning the previous one.
+    pub(crate) fn replace_output_port(&mut self, port: Gc<Port>) -> Gc<Port> {
+        std::mem::replace(&mut self.output, port)
+    }
+
+    /// Compiles `pattern`, reusing the result of previous compilations of the same pattern.
+    #[cfg(feature = "regex")]
+    pub(crate) fn regexp(&mut self, pattern: &str) -> Result<regex::Regex> {
+        if let Some(regexp) = self.regexps.get(pattern) {
+            return Ok(regexp.clone());
+        }
+
+        let regexp =
+            regex::Regex::new(pattern).map_err(|err| Error::InvalidRegexp(err.to_string()))?;
+
+        if self.regexps.len() >= REGEXP_CACHE_SIZE {
+            self.regexps.clear();
+        }
+        self.regexps.insert(pattern.to_owned(), regexp.clone());
+
+        Ok(regexp)
+    }
+
+    /// Parses `text` like [`parse`](crate::parse), remembering where each datum came from so
+    /// that errors raised while evaluating them can be traced back to `name`.
+    pub fn parse_source(&mut self, name: &str, text: &str) -> Result<Vec<Gc<Value>>> {
+        let source = Source::new(name, text);
+        let (values, spans) = parse_spanned(&source)?;
+
+        for (value, span) in spans {
+            let span = Span {
+                source: Rc::clone(&source),
+                range: span.into_range(),
+            };
+            self.spans.insert(&value, span);
+        }
+
+        Ok(values)
+    }
+
+    /// Returns the location of `value` if it was read by [`Environment::parse_source`].
+    pub fn span(&self, value: &Gc<Value>) -> Option<Span> {
+        self.spans.get(value)
+    }
+
+    /// Drops the locations of the data read from the source called `name`, so that errors raised
+    /// by these data are no longer traced back to it. Locations are otherwise dropped along with
+    /// their data.
+    pub fn forget_source(&mut self, name: &str) {
+        self.spans.forget_source(name);
+    }
+
+    /// Evaluates the definitions of `library`, binding them in the global scope.
+    pub fn load_library(&mut self, library: Library) -> Result<()> {
+        let scopes = self.frames.split_off(1);
+
+        // Reloading a library replaces its definitions, and with them the spans of their bodies
+        self.forget_source(library.name());
+
+        let ret = self
+            .parse_source(library.name(), library.source())
+            .and_then(|values| {
+                values
+                    .into_iter()
+                    .try_for_each(|value| eval(value, self).map(|_| ()))
+            });
+
+        self.frames.extend(scopes);
+
+        ret
+    }
 }
 
 impl Default for Environment {
     fn default() -> Self {
         Self {
             frames: vec![HashMap::from_iter(crate::builtin::builtins())],
+            input: Gc::new(Port::input(BufReader::new(std::io::stdin()), false)),
+            output: Gc::new(Port::output(std::io::stdout(), false)),
+            error: Gc::new(Port::output(std::io::stderr(), false)),
+            #[cfg(feature = "regex")]
+            regexps: HashMap::new(),
+            spans: SpanTable::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{Cursor, Write};
+    use std::rc::Rc;
+
+    use crate::error::Result;
+    use crate::{run, Environment};
+
+    /// A writer whose output can still be inspected after it has been given to an environment.
+    #[derive(Clone, Default)]
+    struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Buffer {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    #[test]
+    fn standard_ports() -> Result<()> {
+        let env = &mut Environment::default();
+        let (stdout, stderr) = (Buffer::default(), Buffer::default());
+
+        env.set_stdin(Cursor::new("first line\n(a datum)"));
+        env.set_stdout(stdout.clone());
+        env.set_stderr(stderr.clone());
+
+        assert_eval!(env, "(read-line)", "\"first line\"");
+        assert_eval!(env, "(read)", "(a datum)");
+
+        run("(display \"out\") (newline) (write \"out\")", env)?;
+        run("(display \"err\" (current-error-port))", env)?;
+        run(
+            "(with-output-to-string (lambda () (display \"captured\")))",
+            env,
+        )?;
+
+        assert_eq!(stdout.contents(), "out\n\"out\"");
+        assert_eq!(stderr.contents(), "err");
+
+        Ok(())
+    }
+
+    #[test]
+    fn forgotten_sources() -> Result<()> {
+        let env = &mut Environment::default();
+
+        let kept = env.parse_source("kept.scm", "(a b)")?.remove(0);
+        let forgotten = env.parse_source("forgotten.scm", "(c d)")?.remove(0);
+        env.forget_source("forgotten.scm");
+
+        assert_eq!(env.span(&kept).unwrap().to_string(), "kept.scm:1:1");
+        assert_eq!(env.span(&forgotten), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn collected_spans() -> Result<()> {
+        let env = &mut Environment::default();
+        let kept = env.parse_source("kept.scm", "(a (b #(c)))")?.remove(0);
+        let baseline = env.spans.len();
+        assert_eq!(baseline, 6);
+
+        // Neither the data thrown away while recovering from an error nor those dropped by the
+        // caller stay in the table
+        for _ in 0..100 {
+            env.parse_source("dropped.scm", "(define (f x) (+ x 1)) (f 2) (g #(1 2))")?;
+            assert!(env.parse_source("broken.scm", "(a (b c) #(d").is_err());
         }
+        gc::force_collect();
+
+        assert_eq!(env.spans.len(), baseline);
+        assert_eq!(env.span(&kept).unwrap().to_string(), "kept.scm:1:1");
+
+        Ok(())
     }
 }
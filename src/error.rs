@@ -1,5 +1,8 @@
+use std::rc::Rc;
+
 use gc::Gc;
 
+use crate::span::{Source, Span};
 use crate::value::Value;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -11,34 +14,205 @@ pub enum Error {
     EmptyProcedure,
     ExpectedProcedure(Gc<Value>),
     ExpectedValue(Gc<Value>),
-    IncorrectArity(usize, usize),
-    TypeMismatch(String, Gc<Value>),
+    IncorrectArity {
+        /// The name of the procedure, or `None` if it is anonymous.
+        procedure: Option<String>,
+        expected: usize,
+        found: usize,
+    },
+    TypeMismatch(Type, Gc<Value>),
     ExpectedList(Gc<Value>),
     IndexOutOfBounds(usize),
+    /// A vector, list or string longer than the builtin creating it allows.
+    LengthTooLarge {
+        length: usize,
+        max: usize,
+    },
+    ClosedPort,
+    Io(Rc<std::io::Error>),
+    InvalidCodePoint(f64),
+    InvalidFormat(String),
+    InvalidRegexp(String),
+    KeyNotFound(Gc<Value>),
+    UnknownLibrary(String),
     UnexpectedEndOfInput,
-    ParseError(Vec<(chumsky::span::SimpleSpan, Vec<String>, char)>),
+    ParseError(Vec<SyntaxError>),
+    /// An error raised while evaluating the expression at the given location.
+    At(Box<Error>, Span),
+    /// An error raised inside the procedure calls in the trace, innermost first.
+    Trace(Box<Error>, Vec<Frame>),
+}
+
+/// The kinds of value a procedure can require an argument to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Symbol,
+    Number,
+    String,
+    Character,
+    Boolean,
+    Pair,
+    Vector,
+    HashTable,
+    Regexp,
+    /// A non-negative integer, used as an index or length.
+    Index,
+    Integer,
+    /// An integer from 0 to 255.
+    Byte,
+    Port,
+    InputPort,
+    OutputPort,
+    TextualPort,
+    BinaryPort,
+    TextualOutputPort,
+    StringOutputPort,
+}
+
+/// An error found by the reader.
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    pub span: Span,
+    pub kind: SyntaxErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxErrorKind {
+    /// A character, or the end of the input if `found` is `None`, where something else was
+    /// expected.
+    Unexpected {
+        found: Option<char>,
+        /// Descriptions of what could have appeared instead, without duplicates.
+        expected: Vec<String>,
+    },
+    /// An opening delimiter, such as `(` or `"`, that is never closed.
+    Unclosed(&'static str),
+    /// A `)` without a matching `(`.
+    Unmatched,
+    /// Any other problem, such as an invalid escape sequence.
+    Invalid(String),
 }
 
-impl<'a> Error {
-    pub fn from_parse_errors(parse_errors: Vec<chumsky::error::Rich<'a, char>>) -> Self {
-        let mut errors = Vec::with_capacity(parse_errors.len());
+/// A procedure call that was in progress when an error was raised.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// The name of the procedure, if it was defined with `(define (name ...) ...)`.
+    pub name: Option<String>,
+    /// The location of the call, which is unknown for calls made by builtins such as `map`.
+    pub span: Option<Span>,
+    /// The number of tail calls made in place of the caller before this one.
+    pub elided: usize,
+}
+
+impl Error {
+    pub fn io(err: std::io::Error) -> Self {
+        Self::Io(Rc::new(err))
+    }
+
+    /// An arity error raised by a builtin, which is named by [`Error::in_builtin`] as it
+    /// returns.
+    pub(crate) fn arity(expected: usize, found: usize) -> Self {
+        Self::IncorrectArity {
+            procedure: None,
+            expected,
+            found,
+        }
+    }
+
+    /// Names the builtin `name` in an arity error it raised itself.
+    ///
+    /// Builtins only call other procedures through [`apply`](crate::eval::apply), which adds a
+    /// frame to any error raised by a lambda, so an unnamed arity error that isn't part of a
+    /// trace can only have come from the builtin.
+    pub(crate) fn in_builtin(self, name: &str) -> Self {
+        match self {
+            Self::IncorrectArity {
+                procedure: None,
+                expected,
+                found,
+            } => Self::IncorrectArity {
+                procedure: Some(name.to_owned()),
+                expected,
+                found,
+            },
+            err => err,
+        }
+    }
 
-        for error in parse_errors.into_iter() {
-            let span = *error.span();
+    /// Records that the error was raised at `span`, unless its location is already known.
+    pub fn at(self, span: Option<Span>) -> Self {
+        match span {
+            Some(span) if self.span().is_none() => Self::At(Box::new(self), span),
+            _ => self,
+        }
+    }
 
-            let expected = error
-                .expected()
-                .map(ToString::to_string)
-                .collect::<Vec<_>>();
+    /// Records that the error propagated out of the procedure call `frame`.
+    pub fn in_frame(self, frame: Frame) -> Self {
+        match self {
+            Self::Trace(err, mut trace) => {
+                trace.push(frame);
+                Self::Trace(err, trace)
+            }
+            err => Self::Trace(Box::new(err), vec![frame]),
+        }
+    }
 
-            let Some(found) = error.found() else {
-                return Self::UnexpectedEndOfInput;
-            };
+    /// The error itself, without the location and trace recorded by [`eval`](crate::eval).
+    pub fn root(&self) -> &Self {
+        match self {
+            Self::At(err, _) | Self::Trace(err, _) => err.root(),
+            err => err,
+        }
+    }
 
-            errors.push((span, expected, *found));
+    /// The location of the innermost expression that raised the error, if it is known.
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Self::At(_, span) => Some(span),
+            Self::Trace(err, _) => err.span(),
+            _ => None,
         }
+    }
 
-        Error::ParseError(errors)
+    /// The procedure calls the error propagated out of, innermost first.
+    pub fn trace(&self) -> &[Frame] {
+        match self {
+            Self::Trace(_, trace) => trace,
+            _ => &[],
+        }
+    }
+}
+
+impl SyntaxError {
+    pub(crate) fn from_rich(error: &chumsky::error::Rich<'_, char>, source: &Rc<Source>) -> Self {
+        let kind = match error.reason() {
+            chumsky::error::RichReason::Custom(message) => {
+                SyntaxErrorKind::Invalid(message.clone())
+            }
+            _ => {
+                let mut expected = Vec::new();
+                for pattern in error.expected() {
+                    let pattern = pattern.to_string();
+                    if !expected.contains(&pattern) {
+                        expected.push(pattern);
+                    }
+                }
+
+                SyntaxErrorKind::Unexpected {
+                    found: error.found().copied(),
+                    expected,
+                }
+            }
+        };
+
+        Self {
+            span: Span {
+                source: Rc::clone(source),
+                range: error.span().into_range(),
+            },
+            kind,
+        }
     }
 }
 
@@ -54,8 +228,16 @@ impl std::fmt::Display for Error {
             Self::ExpectedValue(expr) => {
                 write!(f, "expected a value, but found `{expr}`")
             }
-            Self::IncorrectArity(expected, received) => {
-                write!(f, "expected {expected} arguments, but found {received}")
+            Self::IncorrectArity {
+                procedure,
+                expected,
+                found,
+            } => {
+                write!(f, "expected {expected} arguments")?;
+                if let Some(procedure) = procedure {
+                    write!(f, " to `{procedure}`")?;
+                }
+                write!(f, ", but found {found}")
             }
             Self::TypeMismatch(expected, received) => {
                 write!(
@@ -69,17 +251,31 @@ impl std::fmt::Display for Error {
             Self::IndexOutOfBounds(idx) => {
                 write!(f, "index {idx} out of bounds")
             }
+            Self::LengthTooLarge { length, max } => {
+                write!(f, "length {length} is larger than the maximum of {max}")
+            }
+            Self::ClosedPort => write!(f, "cannot use a closed port"),
+            Self::Io(err) => write!(f, "I/O error: {err}"),
+            Self::InvalidCodePoint(n) => {
+                write!(f, "{n} is not a valid Unicode scalar value")
+            }
+            Self::InvalidFormat(message) => write!(f, "invalid format string: {message}"),
+            Self::InvalidRegexp(message) => {
+                write!(f, "invalid regular expression: {message}")
+            }
+            Self::KeyNotFound(key) => {
+                write!(f, "key `{key}` not found in hash table")
+            }
+            Self::UnknownLibrary(name) => write!(f, "no library named `{name}`"),
             Self::UnexpectedEndOfInput => write!(f, "unexpected end of input"),
+            Self::At(err, span) => write!(f, "{span}: {err}"),
+            Self::Trace(err, _) => write!(f, "{err}"),
             Self::ParseError(errors) => {
-                for (span, expected, found) in errors {
-                    if expected.is_empty() {
-                        writeln!(f, "unexpected '{found}' at {span}")?;
-                    } else {
-                        writeln!(
-                            f,
-                            "expected one of {expected:?} at {span} but found '{found}'"
-                        )?;
+                for (i, error) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
                     }
+                    write!(f, "{}: {error}", error.span)?;
                 }
 
                 Ok(())
@@ -87,3 +283,79 @@ impl std::fmt::Display for Error {
         }
     }
 }
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(&**err),
+            // The location and trace are context around the error rather than its cause
+            Self::At(err, _) | Self::Trace(err, _) => err.source(),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Symbol => "symbol",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Character => "character",
+            Self::Boolean => "boolean",
+            Self::Pair => "pair",
+            Self::Vector => "vector",
+            Self::HashTable => "hash table",
+            Self::Regexp => "regexp",
+            Self::Index => "index",
+            Self::Integer => "integer",
+            Self::Byte => "byte",
+            Self::Port => "port",
+            Self::InputPort => "input port",
+            Self::OutputPort => "output port",
+            Self::TextualPort => "textual port",
+            Self::BinaryPort => "binary port",
+            Self::TextualOutputPort => "textual output port",
+            Self::StringOutputPort => "string output port",
+        })
+    }
+}
+
+impl std::fmt::Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(span) = &self.span {
+            write!(f, "{span}: ")?;
+        }
+
+        match &self.name {
+            Some(name) => write!(f, "in `{name}`"),
+            None => write!(f, "in anonymous procedure"),
+        }
+    }
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            SyntaxErrorKind::Unexpected { found, expected } => {
+                match found {
+                    Some(found) => write!(f, "unexpected {found:?}")?,
+                    None => write!(f, "unexpected end of input")?,
+                }
+
+                match expected.as_slice() {
+                    [] => Ok(()),
+                    [expected] => write!(f, ", expected {expected}"),
+                    [init @ .., last] => {
+                        write!(f, ", expected one of {}, or {last}", init.join(", "))
+                    }
+                }
+            }
+            SyntaxErrorKind::Unclosed(delimiter) => write!(f, "unclosed `{delimiter}`"),
+            SyntaxErrorKind::Unmatched => write!(f, "unmatched `)`"),
+            SyntaxErrorKind::Invalid(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for SyntaxError {}
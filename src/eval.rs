@@ -1,107 +1,228 @@
 use gc::Gc;
 
 use crate::environment::Environment;
-use crate::error::{Error, Result};
+use crate::error::{Error, Frame, Result};
 use crate::unscheme;
 use crate::value::{PrimitiveProcedure, Procedure, Value};
 
 pub fn eval_to_value(value: Gc<Value>, env: &mut Environment) -> Result<Gc<Value>> {
     let v = eval(value.clone(), env)?;
     match &*v {
-        Value::Void => Err(Error::ExpectedValue(value)),
+        Value::Void => Err(Error::ExpectedValue(value.clone()).at(env.span(&value))),
         _ => Ok(v),
     }
 }
 
+/// Evaluates `value`. Errors are annotated with the location of the innermost expression being
+/// evaluated when they were raised, if it was read by
+/// [`Environment::parse_source`](crate::Environment::parse_source), and with the procedure calls
+/// they propagated out of.
 pub fn eval(mut value: Gc<Value>, env: &mut Environment) -> Result<Gc<Value>> {
     let initial_stack_depth = env.depth();
+    let mut call = None;
 
+    let ret = eval_tail_calls(&mut value, &mut call, initial_stack_depth, env).map_err(|err| {
+        let err = err.at(env.span(&value));
+        match call {
+            Some(call) => err.in_frame(call.frame(env)),
+            None => err,
+        }
+    });
+
+    env.restore(initial_stack_depth);
+
+    ret
+}
+
+/// A call to a [`Procedure`], recorded cheaply on every call and only turned into a [`Frame`] if
+/// an error propagates out of it.
+struct Call {
+    procedure: Gc<Value>,
+    expression: Gc<Value>,
+    /// The number of tail calls this call replaced.
+    elided: usize,
+}
+
+impl Call {
+    fn frame(&self, env: &Environment) -> Frame {
+        let name = match &*self.procedure {
+            Value::Procedure(procedure) => procedure.name.clone(),
+            _ => None,
+        };
+
+        Frame {
+            name,
+            span: env.span(&self.expression),
+            elided: self.elided,
+        }
+    }
+}
+
+/// Evaluates `value` in place of the expressions it makes tail calls to, so that `value` is the
+/// expression being evaluated if an error is returned, and `call` is the procedure call it is part
+/// of, if any.
+fn eval_tail_calls(
+    value: &mut Gc<Value>,
+    call: &mut Option<Call>,
+    initial_stack_depth: usize,
+    env: &mut Environment,
+) -> Result<Gc<Value>> {
     // Loop only repeats during a tail call
-    let ret: Gc<Value> = 'tail_call: loop {
-        let (procedure, args) = match &*value {
-            Value::Pair((car, cdr)) => (car, cdr),
-            Value::Symbol(name) => break env.get(name)?,
+    loop {
+        let (procedure, args) = match &**value {
+            Value::Pair(pair) => pair.borrow().clone(),
+            Value::Symbol(name) => return env.get(name),
             Value::EmptyList => return Err(Error::EmptyApplication),
-            _ => break value.clone(),
+            _ => return Ok(value.clone()),
         };
 
-        if let Ok(s) = unscheme!(procedure => Symbol) {
+        if let Ok(s) = unscheme!(&procedure => Symbol) {
             match s.as_ref() {
-                "define" => break eval_define(args, env)?,
-                "and" => break eval_and(args, env)?,
-                "or" => break eval_or(args, env)?,
-                "lambda" => break eval_lambda(args, env)?,
-                "quote" => break eval_quote(args)?,
+                "define" => return eval_define(&args, env),
+                "and" => return eval_and(&args, env),
+                "or" => return eval_or(&args, env),
+                "lambda" => return eval_lambda(&args, env),
+                "quote" => return eval_quote(&args),
                 "if" => {
                     let (predicate, (consequent, alternative)) =
-                        unscheme!(args => [any, any, any])?;
+                        unscheme!(&args => [any, any, any])?;
 
-                    value = if eval_to_value(predicate, env)?.to_bool() {
+                    *value = if eval_to_value(predicate, env)?.to_bool() {
                         consequent
                     } else {
                         alternative
                     };
 
-                    continue 'tail_call;
+                    continue;
                 }
                 _ => {}
             }
         }
 
-        let procedure = eval_to_value(procedure.clone(), env)?;
-        let (parameters, body, captures) = match &*procedure {
-            Value::PrimitiveProcedure(PrimitiveProcedure(f)) => break f(args, env)?,
-            Value::Procedure(Procedure {
-                parameters,
-                body,
-                captures,
-            }) => (parameters, body, captures),
-            _ => return Err(Error::ExpectedProcedure(procedure.clone())),
+        let callee = eval_to_value(procedure.clone(), env)?;
+        let procedure = match &*callee {
+            Value::PrimitiveProcedure(PrimitiveProcedure { name, function }) => {
+                let args = eval_args(&args, env)?;
+                return function(&args, env).map_err(|err| err.in_builtin(name));
+            }
+            Value::Procedure(procedure) => procedure,
+            _ => return Err(Error::ExpectedProcedure(callee.clone())),
         };
 
-        let args: Vec<Gc<Value>> = args
-            .map(|arg| eval_to_value(arg?, env))
-            .collect::<Result<_>>()?;
+        let args = eval_args(&args, env)?;
 
-        if args.len() != parameters.len() {
-            return Err(Error::IncorrectArity(parameters.len(), args.len()));
-        }
+        // Tail calls reuse the scope created by the initial call
+        let new_scope = env.depth() == initial_stack_depth;
+        bind_arguments(procedure, args, new_scope, env)?;
+
+        // A tail call replaces the frame of the previous call
+        *call = Some(Call {
+            procedure: callee.clone(),
+            expression: value.clone(),
+            elided: call.as_ref().map_or(0, |call| call.elided + 1),
+        });
+
+        debug_assert!(!procedure.body.is_empty());
+
+        let mut body = procedure.body.clone();
+        let last = body.remove(body.len() - 1);
 
-        if env.depth() == initial_stack_depth {
-            env.new_scope();
+        for expr in body {
+            eval(expr, env)?;
         }
 
-        for (param, arg) in parameters.iter().zip(args) {
-            env.bind(param, arg);
+        *value = last;
+    }
+}
+
+/// Evaluates each of the arguments in the list `args`.
+fn eval_args(args: &Value, env: &mut Environment) -> Result<Vec<Gc<Value>>> {
+    args.iter().map(|arg| eval_to_value(arg?, env)).collect()
+}
+
+/// Applies `procedure` to already evaluated `args`.
+pub fn apply(
+    procedure: &Gc<Value>,
+    args: Vec<Gc<Value>>,
+    env: &mut Environment,
+) -> Result<Gc<Value>> {
+    match &**procedure {
+        Value::PrimitiveProcedure(PrimitiveProcedure { name, function }) => {
+            function(&args, env).map_err(|err| err.in_builtin(name))
         }
+        Value::Procedure(procedure) => {
+            let initial_stack_depth = env.depth();
 
-        for (name, value) in captures {
-            env.bind(name, value.clone());
+            let ret = bind_arguments(procedure, args, true, env).and_then(|()| {
+                let mut ret = Value::void();
+                for expr in &procedure.body {
+                    ret = eval(expr.clone(), env)?;
+                }
+                Ok(ret)
+            });
+
+            env.restore(initial_stack_depth);
+
+            ret.map_err(|err| {
+                err.in_frame(Frame {
+                    name: procedure.name.clone(),
+                    span: None,
+                    elided: 0,
+                })
+            })
         }
+        _ => Err(Error::ExpectedProcedure(procedure.clone())),
+    }
+}
 
-        debug_assert!(!body.is_empty());
+/// Binds the parameters and captured variables of `procedure`, in a new scope if `new_scope`
+/// is set or otherwise in the current one.
+fn bind_arguments(
+    procedure: &Procedure,
+    args: Vec<Gc<Value>>,
+    new_scope: bool,
+    env: &mut Environment,
+) -> Result<()> {
+    let Procedure {
+        parameters,
+        rest,
+        captures,
+        ..
+    } = procedure;
+
+    if args.len() < parameters.len() || (rest.is_none() && args.len() > parameters.len()) {
+        return Err(Error::IncorrectArity {
+            procedure: procedure.name.clone(),
+            expected: parameters.len(),
+            found: args.len(),
+        });
+    }
 
-        let mut body = body.clone();
-        let last = body.remove(body.len() - 1);
+    if new_scope {
+        env.new_scope();
+    }
 
-        for expr in body {
-            eval(expr, env)?;
-        }
+    let mut args = args.into_iter();
 
-        value = last;
+    for (param, arg) in parameters.iter().zip(&mut args) {
+        env.bind(param, arg);
+    }
 
-        continue 'tail_call;
-    };
+    if let Some(rest) = rest {
+        env.bind(rest, Gc::new(args.collect()));
+    }
 
-    env.restore(initial_stack_depth);
+    for (name, value) in captures {
+        env.bind(name, value.clone());
+    }
 
-    Ok(ret)
+    Ok(())
 }
 
 fn eval_define(args: &Gc<Value>, env: &mut Environment) -> Result<Gc<Value>> {
     if let Ok(((ref name, ref params), ref body)) = unscheme!(args => [Pair, rest]) {
         let name = unscheme!(name => Symbol)?;
-        let procedure = make_lambda(params, body, env)?;
+        let procedure = make_lambda(Some(&name), params, body, env)?;
         env.bind(&name, procedure);
     } else {
         let (lhs, rhs) = unscheme!(args => [Symbol, any])?;
@@ -113,7 +234,7 @@ fn eval_define(args: &Gc<Value>, env: &mut Environment) -> Result<Gc<Value>> {
 }
 
 fn eval_and(args: &Gc<Value>, env: &mut Environment) -> Result<Gc<Value>> {
-    for value in args.as_ref() {
+    for value in args.iter() {
         if !eval_to_value(value?, env)?.to_bool() {
             return Ok(Value::boolean(false));
         }
@@ -122,7 +243,7 @@ fn eval_and(args: &Gc<Value>, env: &mut Environment) -> Result<Gc<Value>> {
 }
 
 fn eval_or(args: &Gc<Value>, env: &mut Environment) -> Result<Gc<Value>> {
-    for value in args.as_ref() {
+    for value in args.iter() {
         if eval_to_value(value?, env)?.to_bool() {
             return Ok(Value::boolean(true));
         }
@@ -132,42 +253,68 @@ fn eval_or(args: &Gc<Value>, env: &mut Environment) -> Result<Gc<Value>> {
 
 fn eval_lambda(args: &Gc<Value>, env: &mut Environment) -> Result<Gc<Value>> {
     let (parameters, body) = unscheme!(args => Pair)?;
-    make_lambda(&parameters, &body, env)
+    make_lambda(None, &parameters, &body, env)
 }
 
 fn eval_quote(args: &Gc<Value>) -> Result<Gc<Value>> {
     unscheme!(args => [any])
 }
 
-fn make_lambda(parameters: &Value, body: &Value, env: &mut Environment) -> Result<Gc<Value>> {
-    let parameters = parameters
-        .map(|p| p.and_then(|p| unscheme!(&p => Symbol)))
-        .collect::<Result<Vec<_>>>()?;
+fn make_lambda(
+    name: Option<&str>,
+    parameters: &Gc<Value>,
+    body: &Value,
+    env: &mut Environment,
+) -> Result<Gc<Value>> {
+    // Parameters are either a list of symbols, a single symbol bound to all arguments, or an
+    // improper list of symbols whose tail is bound to the remaining arguments
+    let mut names = Vec::new();
+    let mut tail = parameters.clone();
+    let rest = loop {
+        tail = match &*tail {
+            Value::EmptyList => break None,
+            Value::Symbol(name) => break Some(name.clone()),
+            Value::Pair(pair) => {
+                let (car, cdr) = pair.borrow().clone();
+                names.push(unscheme!(&car => Symbol)?);
+                cdr
+            }
+            _ => return Err(Error::ExpectedList(parameters.clone())),
+        };
+    };
 
-    let body: Vec<_> = body.collect::<Result<_>>()?;
+    let body: Vec<_> = body.iter().collect::<Result<_>>()?;
 
     if body.is_empty() {
         return Err(Error::EmptyProcedure);
     }
 
+    let bound: Vec<_> = names.iter().chain(&rest).cloned().collect();
+
     let mut captures = Vec::new();
     for expr in &body {
-        get_captures(expr, &parameters, env, &mut captures);
+        get_captures(expr, &bound, env, &mut captures);
     }
 
-    Ok(Value::procedure(parameters, body, captures))
+    Ok(Value::procedure(
+        name.map(str::to_owned),
+        names,
+        rest,
+        body,
+        captures,
+    ))
 }
 
 fn get_captures(
-    value: &Value,
+    value: &Gc<Value>,
     parameters: &[String],
     env: &mut Environment,
     captures: &mut Vec<(String, Gc<Value>)>,
 ) {
-    let mut stack = vec![value];
+    let mut stack = vec![value.clone()];
 
     while let Some(v) = stack.pop() {
-        match v {
+        match &*v {
             Value::Symbol(s) => {
                 if !parameters.contains(s) {
                     if let Ok(v) = env.get(s) {
@@ -175,7 +322,8 @@ fn get_captures(
                     }
                 }
             }
-            Value::Pair((car, cdr)) => {
+            Value::Pair(pair) => {
+                let (car, cdr) = pair.borrow().clone();
                 stack.push(car);
                 stack.push(cdr);
             }
@@ -187,6 +335,7 @@ fn get_captures(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::Type;
     use crate::{parse_one, Environment, Value};
 
     #[test]
@@ -201,13 +350,169 @@ mod tests {
         assert!(env.get("x").is_err());
         assert!(env.get("a").is_err());
 
-        eval(parse_one("(define (g f) (eq? f 0))")?, env)?;
+        eval(parse_one("(define (g f) (eqv? f 0))")?, env)?;
 
         assert_eq!(eval(parse_one("(g 0)")?, env)?, Value::boolean(true));
 
         Ok(())
     }
 
+    #[test]
+    fn error_spans() -> Result<()> {
+        let env = &mut Environment::default();
+
+        for value in env.parse_source(
+            "test.scm",
+            "(define (f x)\n  (car x))\n(define (g x) (f x))",
+        )? {
+            eval(value, env)?;
+        }
+
+        let value = env.parse_source("call.scm", "(+ 1 (g 2))")?.remove(0);
+        let err = eval(value, env).unwrap_err();
+        let span = err.span().unwrap();
+
+        assert!(matches!(err.root(), Error::TypeMismatch(..)));
+        assert_eq!(span.source.name(), "test.scm");
+        assert_eq!((span.line(), span.column()), (2, 3));
+        assert_eq!(&span.source.text()[span.range.clone()], "(car x)");
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "test.scm:2:3: {}",
+                Error::TypeMismatch(Type::Pair, Value::number(2.0))
+            )
+        );
+
+        let value = env
+            .parse_source("unbound.scm", "(list 1\n  undefined)")?
+            .remove(0);
+        let err = eval(value, env).unwrap_err();
+        assert_eq!(
+            err.span().map(ToString::to_string).as_deref(),
+            Some("unbound.scm:2:3")
+        );
+
+        // Values that weren't read from a source have no location
+        assert!(eval(parse_one("(car 1)")?, env)
+            .unwrap_err()
+            .span()
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn stack_traces() -> Result<()> {
+        let env = &mut Environment::default();
+
+        let source = "
+            (define (inner x) (car x))
+            (define (middle x) (list (inner x)))
+            (define (tail x) (middle x))
+            (define (outer x) (if #t (tail x) 0))
+            (list (outer 1))
+            (map (lambda (x) (inner x)) '(1))
+        ";
+        let mut values = env.parse_source("trace.scm", source)?;
+        let map = values.pop().unwrap();
+        let call = values.pop().unwrap();
+        for value in values {
+            eval(value, env)?;
+        }
+
+        let err = eval(call, env).unwrap_err();
+        let trace: Vec<_> = err
+            .trace()
+            .iter()
+            .map(|frame| (frame.name.as_deref(), frame.to_string(), frame.elided))
+            .collect();
+
+        // `outer` is replaced by `tail`, which is in turn replaced by `middle`
+        assert_eq!(
+            trace,
+            [
+                (Some("inner"), "trace.scm:3:38: in `inner`".to_owned(), 0),
+                (Some("middle"), "trace.scm:4:30: in `middle`".to_owned(), 2),
+            ]
+        );
+        assert_eq!(err.span().unwrap().to_string(), "trace.scm:2:31");
+
+        let err = eval(map, env).unwrap_err();
+        let names: Vec<_> = err.trace().iter().map(|frame| frame.to_string()).collect();
+        assert_eq!(
+            names,
+            ["trace.scm:7:30: in `inner`", "in anonymous procedure",]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn arity_errors() -> Result<()> {
+        let env = &mut Environment::default();
+
+        eval(parse_one("(define (f x y) x)")?, env)?;
+
+        let mut arity = |input| match eval(parse_one(input).unwrap(), env).unwrap_err().root() {
+            Error::IncorrectArity {
+                procedure,
+                expected,
+                found,
+            } => (procedure.clone(), *expected, *found),
+            err => panic!("expected an arity error, found {err:?}"),
+        };
+
+        assert_eq!(arity("(f 1)"), (Some("f".to_owned()), 2, 1));
+        assert_eq!(arity("((lambda (x) x))"), (None, 1, 0));
+        assert_eq!(arity("(-)"), (Some("-".to_owned()), 1, 0));
+        assert_eq!(arity("(apply - '())"), (Some("-".to_owned()), 1, 0));
+        assert_eq!(arity("(map f '(1))"), (Some("f".to_owned()), 2, 1));
+        assert_eq!(arity("(list ((lambda (x) x)))"), (None, 1, 0));
+        assert_eq!(arity("(map (lambda () 1) '(1))"), (None, 0, 1));
+        assert_eq!(arity("(apply (lambda (x) x) '())"), (None, 1, 0));
+
+        let err = eval(parse_one("(f 1)")?, env).unwrap_err();
+        assert_eq!(
+            err.root().to_string(),
+            "expected 2 arguments to `f`, but found 1"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_sources() {
+        use std::error::Error as _;
+
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        let err = Error::io(io).in_frame(Frame {
+            name: None,
+            span: None,
+            elided: 0,
+        });
+        assert_eq!(err.source().unwrap().to_string(), "no such file");
+
+        assert!(Error::EmptyApplication.source().is_none());
+    }
+
+    #[test]
+    fn rest_parameters() -> Result<()> {
+        let env = &mut Environment::default();
+
+        eval(parse_one("(define f (lambda args args))")?, env)?;
+        eval(
+            parse_one("(define (g a) (lambda args (cons a args)))")?,
+            env,
+        )?;
+
+        assert_eq!(eval(parse_one("(f)")?, env)?, parse_one("()")?);
+        assert_eq!(eval(parse_one("(f 1 2 3)")?, env)?, parse_one("(1 2 3)")?);
+        assert_eq!(eval(parse_one("((g 1) 2 3)")?, env)?, parse_one("(1 2 3)")?);
+
+        Ok(())
+    }
+
     #[test]
     fn closures() -> Result<()> {
         let env = &mut Environment::default();
@@ -0,0 +1,136 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use gc::{Finalize, Gc, Trace};
+
+use crate::builtin::{eq, equal, eqv};
+use crate::value::Value;
+
+/// The equivalence predicate used to compare the keys of a [`HashTable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Equivalence {
+    Eq,
+    Eqv,
+    Equal,
+}
+
+impl Equivalence {
+    pub fn test(self, lhs: &Gc<Value>, rhs: &Gc<Value>) -> bool {
+        match self {
+            Self::Eq => eq(lhs, rhs),
+            Self::Eqv => eqv(lhs, rhs),
+            Self::Equal => equal(lhs, rhs),
+        }
+    }
+
+    /// Hashes a key such that keys considered equivalent by [`Equivalence::test`] always have
+    /// the same hash.
+    pub fn hash(self, key: &Value) -> u64 {
+        let mut state = DefaultHasher::new();
+        let mut budget = HASH_BUDGET;
+        self.hash_into(key, &mut state, &mut budget);
+        state.finish()
+    }
+
+    fn hash_into(self, key: &Value, state: &mut DefaultHasher, budget: &mut usize) {
+        if *budget == 0 {
+            return;
+        }
+        *budget -= 1;
+
+        std::mem::discriminant(key).hash(state);
+
+        match key {
+            Value::Symbol(s) => s.hash(state),
+            Value::Boolean(b) => b.hash(state),
+            Value::EmptyList | Value::Eof => {}
+            // `0.0` and `-0.0` compare equal, so they must hash identically
+            Value::Number(n) if self != Self::Eq => (n + 0.0).to_bits().hash(state),
+            Value::Character(c) if self != Self::Eq => c.hash(state),
+            Value::String(s) if self == Self::Equal => s.borrow().hash(state),
+            Value::Pair(pair) if self == Self::Equal => {
+                let (car, cdr) = pair.borrow().clone();
+                self.hash_into(&car, state, budget);
+                self.hash_into(&cdr, state, budget);
+            }
+            Value::Vector(elements) if self == Self::Equal => {
+                for element in elements.borrow().iter() {
+                    self.hash_into(element, state, budget);
+                }
+            }
+            _ => std::ptr::hash(key, state),
+        }
+    }
+}
+
+/// The maximum number of values visited when hashing a structure for `equal?`, which keeps
+/// hashing cheap for large keys and ensures it terminates for cyclic ones.
+const HASH_BUDGET: usize = 64;
+
+type Bucket = Vec<(Gc<Value>, Gc<Value>)>;
+
+#[derive(Debug, Clone, PartialEq, Trace, Finalize)]
+pub struct HashTable {
+    #[unsafe_ignore_trace]
+    equivalence: Equivalence,
+    buckets: HashMap<u64, Bucket>,
+    len: usize,
+}
+
+impl HashTable {
+    pub fn new(equivalence: Equivalence) -> Self {
+        Self {
+            equivalence,
+            buckets: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    pub fn get(&self, key: &Gc<Value>) -> Option<Gc<Value>> {
+        self.buckets
+            .get(&self.equivalence.hash(key))?
+            .iter()
+            .find(|(k, _)| self.equivalence.test(k, key))
+            .map(|(_, v)| v.clone())
+    }
+
+    pub fn insert(&mut self, key: Gc<Value>, value: Gc<Value>) {
+        let bucket = self.buckets.entry(self.equivalence.hash(&key)).or_default();
+
+        match bucket
+            .iter_mut()
+            .find(|(k, _)| self.equivalence.test(k, &key))
+        {
+            Some((_, v)) => *v = value,
+            None => {
+                bucket.push((key, value));
+                self.len += 1;
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &Gc<Value>) -> Option<Gc<Value>> {
+        let hash = self.equivalence.hash(key);
+        let bucket = self.buckets.get_mut(&hash)?;
+        let idx = bucket
+            .iter()
+            .position(|(k, _)| self.equivalence.test(k, key))?;
+
+        let (_, value) = bucket.swap_remove(idx);
+        if bucket.is_empty() {
+            self.buckets.remove(&hash);
+        }
+        self.len -= 1;
+
+        Some(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Gc<Value>, &Gc<Value>)> {
+        self.buckets.values().flatten().map(|(k, v)| (k, v))
+    }
+}
@@ -1,18 +1,50 @@
 #![feature(let_chains)]
 
+/// Asserts that evaluating `$input` results in the value read from `$expected`.
+#[cfg(test)]
+macro_rules! assert_eval {
+    ($env:expr, $input:expr, $expected:expr) => {
+        assert_eq!(
+            $crate::run($input, $env)?,
+            $crate::parse_one($expected)?,
+            "{}",
+            $input,
+        );
+    };
+}
+
 mod builtin;
+mod diagnostic;
 mod environment;
 mod error;
 mod eval;
+mod hash_table;
+mod library;
 mod parse;
+mod port;
+mod span;
 mod value;
 
+pub use diagnostic::Report;
 pub use environment::Environment;
-pub use error::Error;
-pub use eval::{eval, eval_to_value};
+pub use error::{Error, Frame, SyntaxError, SyntaxErrorKind, Type};
+pub use eval::{apply, eval, eval_to_value};
 pub use gc::{self, Gc};
+pub use library::Library;
 pub use parse::{parse, parse_one};
-pub use value::Value;
+pub use port::Port;
+pub use span::{Source, Span};
+pub use value::{Style, Value};
+
+/// Evaluates each expression in `input`, returning the value of the last one.
+#[cfg(test)]
+fn run(input: &str, env: &mut Environment) -> error::Result<Gc<Value>> {
+    let mut ret = Value::void();
+    for value in parse(input)? {
+        ret = eval(value, env)?;
+    }
+    Ok(ret)
+}
 
 /// Constructs an Gc<[Value](Value)> using S-expression syntax.
 ///
@@ -75,15 +107,51 @@ macro_rules! scheme {
 
 #[macro_export]
 macro_rules! unscheme {
+    // Check the number of arguments before destructuring them
+    (@arity $args:ident, $n:expr, []) => {
+        match $args.len() {
+            found if found == $n => Ok(()),
+            found => Err($crate::Error::IncorrectArity { procedure: None, expected: $n, found }),
+        }
+    };
+    (@arity $args:ident, $n:expr, [rest]) => {
+        match $args.len() {
+            found if found < $n => {
+                Err($crate::Error::IncorrectArity { procedure: None, expected: $n, found })
+            }
+            _ => Ok(()),
+        }
+    };
+    (@arity $args:ident, $n:expr, [$variant:ident $(, $rest:ident)*]) => {
+        unscheme!(@arity $args, $n + 1, [$($rest),*])
+    };
+    (@args $args:ident => [rest]) => { Ok($args) };
+    (@args $args:ident => [$variant:ident]) => { unscheme!(&$args[0] => $variant) };
+    (@args $args:ident => [$variant:ident, $($rest:ident),*]) => {
+        Ok((unscheme!(&$args[0] => $variant)?, {
+            let $args = &$args[1..];
+            unscheme!(@args $args => [$($rest),*])?
+        }))
+    };
+
     ($value:expr => any) => { Ok($value.clone()) };
     ($value:expr => [rest]) => { Ok($value.clone()) };
+    ($value:expr => Pair) => {
+        match &**$value {
+            $crate::Value::Pair(pair) => Ok(pair.borrow().clone()),
+            _ => Err($crate::Error::TypeMismatch($crate::Type::Pair, $value.clone())),
+        }
+    };
+    ($value:expr => String) => {
+        match &**$value {
+            $crate::Value::String(s) => Ok(s.borrow().clone()),
+            _ => Err($crate::Error::TypeMismatch($crate::Type::String, $value.clone())),
+        }
+    };
     ($value:expr => $variant:ident) => {
         match &**$value {
             $crate::Value::$variant(inner) => Ok(inner.clone()),
-            _ => Err($crate::Error::TypeMismatch(
-                stringify!($variant).to_string().to_lowercase(),
-                $value.clone()
-            )),
+            _ => Err($crate::Error::TypeMismatch($crate::Type::$variant, $value.clone())),
         }
     };
     ($value:expr => [$variant:ident]) => {
@@ -101,6 +169,13 @@ macro_rules! unscheme {
             )))
     };
 
+    // Destructure a slice of evaluated arguments, as passed to primitive procedures
+    ($args:expr => &[$($variant:ident),+]) => {{
+        let args: &[$crate::Gc<$crate::Value>] = $args;
+        unscheme!(@arity args, 0, [$($variant),+])
+            .and_then(|()| unscheme!(@args args => [$($variant),+]))
+    }};
+
     // Evaluate before destructuring
     ($value:expr, $env:ident ==> any) => { $crate::eval_to_value($value.clone(), $env) };
     ($value:expr, $env:ident ==> [rest]) => { Ok($value.clone()) };
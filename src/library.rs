@@ -0,0 +1,137 @@
+/// A library of procedures written in Scheme and embedded in the crate, which can be loaded into
+/// an [`Environment`](crate::Environment) on demand with
+/// [`Environment::load_library`](crate::Environment::load_library).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Library {
+    /// The SRFI-1 list library.
+    ///
+    /// Procedures that return multiple values in SRFI-1, such as `span` and `partition`, return
+    /// a two-element list instead.
+    Srfi1,
+}
+
+impl Library {
+    /// Looks up a library by the name used by `load-library`, e.g. `srfi-1`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "srfi-1" => Some(Self::Srfi1),
+            _ => None,
+        }
+    }
+
+    /// The name of the library, as accepted by [`Library::from_name`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Srfi1 => "srfi-1",
+        }
+    }
+
+    /// The Scheme source of the library.
+    pub fn source(self) -> &'static str {
+        match self {
+            Self::Srfi1 => include_str!("library/srfi-1.scm"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Library;
+    use crate::error::Result;
+    use crate::{run, Environment};
+
+    #[test]
+    fn srfi_1() -> Result<()> {
+        let env = &mut Environment::default();
+        env.load_library(Library::Srfi1)?;
+
+        assert_eval!(env, "(cons* 1 2 '(3 4))", "(1 2 3 4)");
+        assert_eval!(env, "(list-tabulate 4 (lambda (i) (* i i)))", "(0 1 4 9)");
+        assert_eval!(env, "(iota 5)", "(0 1 2 3 4)");
+        assert_eval!(env, "(iota 5 1)", "(1 2 3 4 5)");
+        assert_eval!(env, "(iota 3 0 -2)", "(0 -2 -4)");
+        assert_eval!(env, "(third '(a b c d))", "c");
+        assert_eval!(env, "(tenth (iota 10))", "9");
+        assert_eval!(env, "(take '(a b c d e) 2)", "(a b)");
+        assert_eval!(env, "(drop '(a b c d e) 2)", "(c d e)");
+        assert_eval!(env, "(take-right '(a b c d e) 2)", "(d e)");
+        assert_eval!(env, "(drop-right '(a b c d e) 2)", "(a b c)");
+        assert_eval!(env, "(split-at '(a b c d e) 2)", "((a b) (c d e))");
+        assert_eval!(env, "(last '(1 2 3))", "3");
+        assert_eval!(env, "(last-pair '(1 2 3))", "(3)");
+
+        run("(define (zero? n) (eqv? n 0))", env)?;
+        assert_eval!(env, "(take-while zero? '(0 0 1 0))", "(0 0)");
+        assert_eval!(env, "(drop-while zero? '(0 0 1 0))", "(1 0)");
+        assert_eval!(env, "(span zero? '(0 0 1 0))", "((0 0) (1 0))");
+        assert_eval!(env, "(break zero? '(1 2 0 3))", "((1 2) (0 3))");
+        assert_eval!(env, "(partition zero? '(0 1 0 2))", "((0 0) (1 2))");
+
+        assert_eval!(env, "(concatenate '((a) (b c) ()))", "(a b c)");
+        assert_eval!(env, "(append-reverse '(3 2 1) '(4 5))", "(1 2 3 4 5)");
+        assert_eval!(env, "(count zero? '(0 1 0))", "2");
+        assert_eval!(env, "(count eqv? '(1 2 3) '(1 0 3 4))", "2");
+        assert_eval!(
+            env,
+            "(append-map (lambda (x) (list x x)) '(a b))",
+            "(a a b b)"
+        );
+        assert_eval!(
+            env,
+            "(filter-map (lambda (x) (if (zero? x) #f (* x 2))) '(0 1 2))",
+            "(2 4)"
+        );
+        assert_eval!(env, "(find zero? '(3 0 2))", "0");
+        assert_eval!(env, "(find zero? '(3 2))", "#f");
+        assert_eval!(env, "(find-tail zero? '(3 0 2))", "(0 2)");
+        assert_eval!(
+            env,
+            "(any (lambda (x) (if (zero? x) 'yes #f)) '(1 0))",
+            "yes"
+        );
+        assert_eval!(env, "(any eqv? '(1 2) '(3 2))", "#t");
+        assert_eval!(env, "(every zero? '())", "#t");
+        assert_eval!(env, "(every (lambda (x) x) '(1 2 3))", "3");
+        assert_eval!(env, "(every zero? '(0 1))", "#f");
+        assert_eval!(env, "(list-index zero? '(3 2 0))", "2");
+
+        assert_eval!(env, "(delete 'a '(a b a c))", "(b c)");
+        assert_eval!(env, "(delete-duplicates '(a b a c b))", "(a b c)");
+        assert_eval!(env, "(alist-cons 'a '(1) '((b 2)))", "((a 1) (b 2))");
+        assert_eval!(env, "(alist-delete 'a '((a 1) (b 2) (a 3)))", "((b 2))");
+
+        assert_eval!(env, "(lset-adjoin eq? '(a b c) 'd 'a)", "(d a b c)");
+        assert_eval!(
+            env,
+            "(lset-union eq? '(a b c d e) '(a e i o u))",
+            "(u o i a b c d e)"
+        );
+        assert_eval!(
+            env,
+            "(lset-intersection eq? '(a b c d e) '(a e i o u))",
+            "(a e)"
+        );
+        assert_eval!(
+            env,
+            "(lset-difference eq? '(a b c d e) '(a e i o u))",
+            "(b c d)"
+        );
+        assert_eval!(env, "(lset<= eq? '(a) '(a b a) '(a b c c))", "#t");
+        assert_eval!(env, "(lset= eq? '(b e a) '(a e b) '(e e b a))", "#t");
+        assert_eval!(env, "(lset= eq? '(a) '(a b))", "#f");
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_library_builtin() -> Result<()> {
+        let env = &mut Environment::default();
+
+        assert!(run("(first '(1 2))", env).is_err());
+        run("(load-library 'srfi-1)", env)?;
+        assert_eval!(env, "(first '(1 2))", "1");
+        assert!(run("(load-library 'srfi-0)", env).is_err());
+
+        Ok(())
+    }
+}
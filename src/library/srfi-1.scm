@@ -0,0 +1,200 @@
+(define (%optional args default)
+  (if (null? args) default (car args)))
+
+(define (%any-null? lists)
+  (if (null? lists) #f (if (pair? (car lists)) (%any-null? (cdr lists)) #t)))
+
+(define (%car-if-pair x)
+  (if (pair? x) (car x) #f))
+
+(define (xcons d a) (cons a d))
+
+(define (%cons* args)
+  (if (null? (cdr args)) (car args) (cons (car args) (%cons* (cdr args)))))
+
+(define cons* (lambda args (%cons* args)))
+
+(define (%list-tabulate i init-proc acc)
+  (if (eqv? i -1) acc (%list-tabulate (- i 1) init-proc (cons (init-proc i) acc))))
+
+(define (list-tabulate n init-proc)
+  (%list-tabulate (- n 1) init-proc '()))
+
+(define (%iota i start step acc)
+  (if (eqv? i -1) acc (%iota (- i 1) start step (cons (+ start (* i step)) acc))))
+
+(define iota
+  (lambda args
+    (%iota (- (car args) 1)
+           (%optional (cdr args) 0)
+           (%optional (if (null? (cdr args)) '() (cddr args)) 1)
+           '())))
+
+(define (first x) (car x))
+(define (second x) (cadr x))
+(define (third x) (caddr x))
+(define (fourth x) (cadddr x))
+(define (fifth x) (car (cddddr x)))
+(define (sixth x) (cadr (cddddr x)))
+(define (seventh x) (caddr (cddddr x)))
+(define (eighth x) (cadddr (cddddr x)))
+(define (ninth x) (car (cddddr (cddddr x))))
+(define (tenth x) (cadr (cddddr (cddddr x))))
+
+(define (%take x k acc)
+  (if (eqv? k 0) (reverse acc) (%take (cdr x) (- k 1) (cons (car x) acc))))
+
+(define (take x k) (%take x k '()))
+
+(define (drop x k) (list-tail x k))
+
+(define (%take-right lag lead)
+  (if (pair? lead) (%take-right (cdr lag) (cdr lead)) lag))
+
+(define (take-right x k) (%take-right x (drop x k)))
+
+(define (%drop-right lag lead acc)
+  (if (pair? lead) (%drop-right (cdr lag) (cdr lead) (cons (car lag) acc)) (reverse acc)))
+
+(define (drop-right x k) (%drop-right x (drop x k) '()))
+
+(define (split-at x k) (list (take x k) (drop x k)))
+
+(define (last-pair x)
+  (if (pair? (cdr x)) (last-pair (cdr x)) x))
+
+(define (last x) (car (last-pair x)))
+
+(define (%take-while pred x acc)
+  (if (null? x)
+      (reverse acc)
+      (if (pred (car x)) (%take-while pred (cdr x) (cons (car x) acc)) (reverse acc))))
+
+(define (take-while pred x) (%take-while pred x '()))
+
+(define (drop-while pred x)
+  (if (null? x) x (if (pred (car x)) (drop-while pred (cdr x)) x)))
+
+(define (span pred x) (list (take-while pred x) (drop-while pred x)))
+
+(define (break pred x) (span (lambda (e) (if (pred e) #f #t)) x))
+
+(define (%partition pred x in out)
+  (if (null? x)
+      (list (reverse in) (reverse out))
+      (if (pred (car x))
+          (%partition pred (cdr x) (cons (car x) in) out)
+          (%partition pred (cdr x) in (cons (car x) out)))))
+
+(define (partition pred x) (%partition pred x '() '()))
+
+(define (concatenate lists) (apply append lists))
+
+(define (append-reverse rev-head tail)
+  (if (null? rev-head) tail (append-reverse (cdr rev-head) (cons (car rev-head) tail))))
+
+(define (%count pred lists n)
+  (if (%any-null? lists)
+      n
+      (%count pred (map cdr lists) (if (apply pred (map car lists)) (+ n 1) n))))
+
+(define count (lambda args (%count (car args) (cdr args) 0)))
+
+(define append-map (lambda args (apply append (apply map args))))
+
+(define filter-map (lambda args (filter (lambda (x) x) (apply map args))))
+
+(define (find-tail pred x)
+  (if (null? x) #f (if (pred (car x)) x (find-tail pred (cdr x)))))
+
+(define (find pred x) (%car-if-pair (find-tail pred x)))
+
+(define (%any pred lists)
+  (if (%any-null? lists) #f (%any-result pred lists (apply pred (map car lists)))))
+
+(define (%any-result pred lists result)
+  (if result result (%any pred (map cdr lists))))
+
+(define any (lambda args (%any (car args) (cdr args))))
+
+(define (%every pred lists last)
+  (if (%any-null? lists) last (%every-result pred lists (apply pred (map car lists)))))
+
+(define (%every-result pred lists result)
+  (if result (%every pred (map cdr lists) result) #f))
+
+(define every (lambda args (%every (car args) (cdr args) #t)))
+
+(define (%list-index pred lists i)
+  (if (%any-null? lists)
+      #f
+      (if (apply pred (map car lists)) i (%list-index pred (map cdr lists) (+ i 1)))))
+
+(define list-index (lambda args (%list-index (car args) (cdr args) 0)))
+
+(define (%delete x lst =) (remove (lambda (y) (= x y)) lst))
+
+(define delete
+  (lambda args (%delete (car args) (cadr args) (%optional (cddr args) equal?))))
+
+(define (%delete-duplicates lst = acc)
+  (if (null? lst)
+      (reverse acc)
+      (%delete-duplicates (%delete (car lst) (cdr lst) =) = (cons (car lst) acc))))
+
+(define delete-duplicates
+  (lambda args (%delete-duplicates (car args) (%optional (cdr args) equal?) '())))
+
+(define (alist-cons key datum alist) (cons (cons key datum) alist))
+
+(define (alist-copy alist) (map (lambda (entry) (cons (car entry) (cdr entry))) alist))
+
+(define (%alist-delete key alist =) (remove (lambda (entry) (= key (car entry))) alist))
+
+(define alist-delete
+  (lambda args (%alist-delete (car args) (cadr args) (%optional (cddr args) equal?))))
+
+(define (%lset-adjoin = lst elts)
+  (if (null? elts)
+      lst
+      (%lset-adjoin = (if (member (car elts) lst =) lst (cons (car elts) lst)) (cdr elts))))
+
+(define lset-adjoin (lambda args (%lset-adjoin (car args) (cadr args) (cddr args))))
+
+(define (%lset-union = acc lists)
+  (if (null? lists) acc (%lset-union = (%lset-adjoin = acc (car lists)) (cdr lists))))
+
+(define lset-union
+  (lambda args
+    (if (null? (cdr args)) '() (%lset-union (car args) (cadr args) (cddr args)))))
+
+(define (%lset-intersection = lst lists)
+  (filter (lambda (x) (every (lambda (l) (member x l =)) lists)) lst))
+
+(define lset-intersection
+  (lambda args (%lset-intersection (car args) (cadr args) (cddr args))))
+
+(define (%lset-difference = lst lists)
+  (remove (lambda (x) (any (lambda (l) (member x l =)) lists)) lst))
+
+(define lset-difference
+  (lambda args (%lset-difference (car args) (cadr args) (cddr args))))
+
+(define (%subset? = a b)
+  (every (lambda (x) (member x b =)) a))
+
+(define (%lset<= = lists)
+  (if (if (null? lists) #t (null? (cdr lists)))
+      #t
+      (if (%subset? = (car lists) (cadr lists)) (%lset<= = (cdr lists)) #f)))
+
+(define lset<= (lambda args (%lset<= (car args) (cdr args))))
+
+(define (%lset= = lists)
+  (if (if (null? lists) #t (null? (cdr lists)))
+      #t
+      (if (%subset? = (car lists) (cadr lists))
+          (if (%subset? = (cadr lists) (car lists)) (%lset= = (cdr lists)) #f)
+          #f)))
+
+(define lset= (lambda args (%lset= (car args) (cdr args))))
@@ -1,31 +1,257 @@
+use std::collections::HashSet;
+use std::ops::Range;
+use std::rc::Rc;
+
 use chumsky::prelude::*;
-use chumsky::text::int;
-use gc::Gc;
+use chumsky::text::digits;
+use gc::{Gc, GcCell};
 use text::whitespace;
 
-use crate::error::{Error, Result};
-use crate::value::Value;
+use crate::error::{Error, Result, SyntaxError, SyntaxErrorKind};
+use crate::span::{Source, Span};
+use crate::value::{Value, CHARACTER_NAMES, STRING_ESCAPES};
+
+/// Every datum parsed so far, along with its span.
+type Spans = Vec<(Gc<Value>, SimpleSpan)>;
+
+type Extra<'a> = extra::Full<Rich<'a, char>, Spans, ()>;
 
 pub fn parse_one(input: &str) -> Result<Gc<Value>> {
-    parser()
-        .parse(input.trim())
+    let datum = parser();
+    let atmosphere = atmosphere(datum.clone());
+
+    atmosphere
+        .clone()
+        .ignore_then(datum)
+        .then_ignore(atmosphere)
+        .parse(input)
+        .into_result()
+        .map_err(|errors| syntax_errors(errors, &Source::new("<input>", input)))
+}
+
+/// Parses the datum at the start of `input`, returning it along with the byte offset at which it
+/// ends. Any input following the datum is ignored.
+///
+/// Returns `None` if `input` is a valid but incomplete datum, i.e. every error occurs in the
+/// trailing whitespace or at the end of the input.
+pub(crate) fn parse_prefix(input: &str) -> Result<Option<(Gc<Value>, usize)>> {
+    let content = input.trim_end().len();
+    let datum = parser();
+
+    match atmosphere(datum.clone())
+        .ignore_then(datum)
+        .map_with(|value, e| (value, e.span().end))
+        .lazy()
+        .parse(input)
         .into_result()
-        .map_err(Error::from_parse_errors)
+    {
+        Ok(datum) => Ok(Some(datum)),
+        Err(errors) if errors.iter().all(|err| err.span().start >= content) => Ok(None),
+        Err(errors) => Err(syntax_errors(errors, &Source::new("<input>", input))),
+    }
 }
 
 pub fn parse(input: &str) -> Result<Vec<Gc<Value>>> {
-    let program_parser = parser()
-        .separated_by(whitespace())
-        .collect()
+    parse_spanned(&Source::new("<input>", input)).map(|(values, _)| values)
+}
+
+/// Parses a sequence of datums like [`parse`], additionally returning the span of every datum
+/// within them, including nested ones.
+///
+/// Parsing continues after a syntax error, so that every error in the input is reported.
+pub(crate) fn parse_spanned(source: &Rc<Source>) -> Result<(Vec<Gc<Value>>, Spans)> {
+    let input = source.text();
+    let datum = parser();
+    let atmosphere = atmosphere(datum.clone());
+
+    // After an error outside any list, skip ahead until another datum can be parsed
+    let program_parser = atmosphere
+        .clone()
+        .ignore_then(
+            datum
+                .recover_with(skip_then_retry_until(any().ignored(), end()))
+                .then_ignore(atmosphere)
+                .repeated()
+                .collect(),
+        )
         .then_ignore(end());
 
-    program_parser
-        .parse(input.trim())
+    let mut spans = Spans::new();
+    let values: Vec<_> = program_parser
+        .parse_with_state(input, &mut spans)
         .into_result()
-        .map_err(Error::from_parse_errors)
+        .map_err(|errors| syntax_errors(errors, source))?;
+
+    let spans = reachable(&values, spans);
+    Ok((values, spans))
+}
+
+/// Keeps the spans of the data within `values`, dropping those of data that were built while
+/// backtracking or recovering from an error and then thrown away.
+fn reachable(values: &[Gc<Value>], spans: Spans) -> Spans {
+    let mut seen = HashSet::new();
+    let mut stack = values.to_vec();
+
+    while let Some(value) = stack.pop() {
+        if !seen.insert(&*value as *const Value) {
+            continue;
+        }
+        match &*value {
+            Value::Pair(pair) => {
+                let (car, cdr) = pair.borrow().clone();
+                stack.extend([car, cdr]);
+            }
+            Value::Vector(elements) => stack.extend(elements.borrow().iter().cloned()),
+            _ => {}
+        }
+    }
+
+    spans
+        .into_iter()
+        .filter(|(value, _)| seen.contains(&(&**value as *const Value)))
+        .collect()
+}
+
+/// Converts the errors from parsing `source`.
+///
+/// The parser only notices an unbalanced delimiter where it runs out of input or finds an
+/// unexpected `)`, so errors there are replaced by ones pointing at the delimiter itself.
+fn syntax_errors(errors: Vec<Rich<'_, char>>, source: &Rc<Source>) -> Error {
+    let text = source.text();
+    let content = text.trim_end().len();
+    let unbalanced = unbalanced_delimiters(text);
+    let unclosed = unbalanced
+        .iter()
+        .any(|(_, kind)| matches!(kind, SyntaxErrorKind::Unclosed(_)));
+
+    let mut errors: Vec<_> = errors
+        .iter()
+        .filter(|err| !(unclosed && err.span().start >= content))
+        .filter(|err| {
+            !unbalanced
+                .iter()
+                .any(|(range, _)| range.start == err.span().start)
+        })
+        .map(|err| SyntaxError::from_rich(err, source))
+        .collect();
+
+    errors.extend(unbalanced.into_iter().map(|(range, kind)| SyntaxError {
+        span: Span {
+            source: Rc::clone(source),
+            range,
+        },
+        kind,
+    }));
+
+    errors.sort_by_key(|err| err.span.range.start);
+
+    Error::ParseError(errors)
 }
 
-fn parser<'a>() -> impl Parser<'a, &'a str, Gc<Value>, extra::Err<Rich<'a, char>>> {
+/// Finds the parentheses, strings, `|symbols|` and block comments in `input` that are never
+/// closed, and any `)` that closes nothing.
+fn unbalanced_delimiters(input: &str) -> Vec<(Range<usize>, SyntaxErrorKind)> {
+    let mut errors = Vec::new();
+    let mut open: Vec<(usize, &'static str)> = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            ';' => while chars.next_if(|&(_, c)| c != '\n').is_some() {},
+            '"' | '|' => {
+                let mut closed = false;
+                while let Some((_, next)) = chars.next() {
+                    if next == '\\' {
+                        chars.next();
+                    } else if next == c {
+                        closed = true;
+                        break;
+                    }
+                }
+
+                if !closed {
+                    let delimiter = if c == '"' { "\"" } else { "|" };
+                    errors.push((i..i + 1, SyntaxErrorKind::Unclosed(delimiter)));
+                }
+            }
+            '#' => match chars.peek().map(|&(_, c)| c) {
+                // A character such as `#\(`
+                Some('\\') => {
+                    chars.next();
+                    chars.next();
+                }
+                Some('(') => {
+                    chars.next();
+                    open.push((i, "#("));
+                }
+                Some('|') => {
+                    chars.next();
+                    let mut depth = 1;
+                    while depth > 0 {
+                        match chars.next().map(|(_, c)| c) {
+                            Some('#') if chars.next_if(|&(_, c)| c == '|').is_some() => depth += 1,
+                            Some('|') if chars.next_if(|&(_, c)| c == '#').is_some() => depth -= 1,
+                            Some(_) => {}
+                            None => break,
+                        }
+                    }
+
+                    if depth > 0 {
+                        errors.push((i..i + 2, SyntaxErrorKind::Unclosed("#|")));
+                    }
+                }
+                _ => {}
+            },
+            '(' => open.push((i, "(")),
+            ')' if open.pop().is_none() => errors.push((i..i + 1, SyntaxErrorKind::Unmatched)),
+            _ => {}
+        }
+    }
+
+    errors.extend(
+        open.into_iter()
+            .map(|(i, delimiter)| (i..i + delimiter.len(), SyntaxErrorKind::Unclosed(delimiter))),
+    );
+
+    errors
+}
+
+/// Whitespace and comments, which may appear before and after any datum: `;` line comments,
+/// nestable `#| ... |#` block comments, and `#;` comments, which skip the following `datum`.
+fn atmosphere<'a>(
+    datum: impl Parser<'a, &'a str, Gc<Value>, Extra<'a>> + Clone + 'a,
+) -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
+    let line_comment = just(';').then(none_of('\n').repeated()).ignored();
+
+    // An unterminated block comment extends to the end of the input, where it is reported.
+    // Failing outright instead would report the error at the start of the comment, as if it
+    // were an invalid datum.
+    let unterminated = end().validate(|_, e, emitter| {
+        emitter.emit(Rich::custom(e.span(), "unterminated block comment"))
+    });
+
+    let block_comment = recursive(|block_comment| {
+        let text = any().and_is(just("|#").not()).ignored();
+        just("#|")
+            .then(choice((block_comment, text)).repeated())
+            .then(choice((just("|#").ignored(), unterminated)))
+            .ignored()
+    });
+
+    recursive(|atmosphere| {
+        let datum_comment = just("#;").then(atmosphere).then(datum).ignored();
+
+        choice((
+            whitespace().at_least(1),
+            line_comment,
+            block_comment,
+            datum_comment,
+        ))
+        .repeated()
+    })
+}
+
+fn parser<'a>() -> impl Parser<'a, &'a str, Gc<Value>, Extra<'a>> + Clone {
     recursive(|expression| {
         let boolean = choice((
             just("#t").then(just("rue").or_not()).to(true),
@@ -34,84 +260,195 @@ fn parser<'a>() -> impl Parser<'a, &'a str, Gc<Value>, extra::Err<Rich<'a, char>
         .map(Value::Boolean)
         .labelled("boolean");
 
+        let character_name = CHARACTER_NAMES
+            .iter()
+            .map(|&(name, c)| just(name).to(c))
+            .collect::<Vec<_>>();
+
+        // A Unicode scalar value written in hexadecimal, e.g. `x3bb`
+        let scalar_value = just('x')
+            .ignore_then(digits(16).to_slice())
+            .try_map(|digits, span| {
+                u32::from_str_radix(digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| Rich::custom(span, "invalid Unicode scalar value"))
+            });
+
         let character = just("#\\")
-            .ignore_then(choice((
-                just("newline").to('\n'),
-                just("space").to(' '),
-                any(),
-            )))
+            .ignore_then(choice((choice(character_name), scalar_value, any())))
             .map(Value::Character)
             .labelled("character");
 
-        let number = just('-')
+        let special_number = choice((
+            just("+inf.0").to(f64::INFINITY),
+            just("-inf.0").to(f64::NEG_INFINITY),
+            just("+nan.0").to(f64::NAN),
+            just("-nan.0").to(f64::NAN),
+        ));
+
+        let decimal = just('-')
             .or_not()
             .then(choice((
-                int(10).then(just('.')).then(int(10)).to(()),
-                int(10).then(just('.')).to(()),
-                int(10).to(()),
-                just('.').then(int(10)).to(()),
+                digits(10).then(just('.')).then(digits(10)).to(()),
+                digits(10).then(just('.')).to(()),
+                digits(10).to(()),
+                just('.').then(digits(10)).to(()),
             )))
             .to_slice()
             .map(str::parse)
-            .unwrapped()
+            .unwrapped();
+
+        let number = choice((special_number, decimal))
             .map(Value::Number)
             .labelled("number");
 
-        let escape = just('\\').ignore_then(choice((
-            just('n').to('\n'),
-            just('t').to('\t'),
-            just('"'),
-            just('\\'),
-        )));
+        let escape_sequence = STRING_ESCAPES
+            .iter()
+            .map(|&(escape, c)| just(escape).to(c))
+            .collect::<Vec<_>>();
+
+        // A backslash at the end of a line skips the line break and any surrounding indentation
+        let line_continuation = text::inline_whitespace()
+            .then(text::newline())
+            .then(text::inline_whitespace());
+
+        // An invalid escape is skipped so that the rest of the string is still read
+        let escape = just('\\')
+            .ignore_then(choice((
+                choice(escape_sequence).map(Some),
+                scalar_value.then_ignore(just(';')).map(Some),
+                line_continuation.to(None),
+            )))
+            .recover_with(via_parser(just('\\').ignore_then(any()).to(None)));
+
+        // Symbols between bars may contain the same escapes as strings, as well as `\|`
+        let symbol_escape = choice((just("\\|").to(Some('|')), escape.clone()));
 
-        let string = choice((none_of("\\\""), escape))
+        let string = choice((none_of("\\\"").map(Some), escape))
             .repeated()
-            .collect()
+            .collect::<Vec<_>>()
             .padded_by(just('"'))
-            .map(Value::String)
+            .map(|chars| Value::String(GcCell::new(chars.into_iter().flatten().collect())))
             .labelled("string");
 
         let symbol = choice((
-            none_of("|\\")
+            choice((none_of("|\\").map(Some), symbol_escape))
+                .repeated()
+                .collect::<Vec<_>>()
+                .padded_by(just('|'))
+                .map(|chars| chars.into_iter().flatten().collect()),
+            // A lone `.` marks the tail of an improper list
+            none_of(" \t\r\n|()\";'#")
                 .repeated()
                 .at_least(1)
                 .to_slice()
-                .padded_by(just('|')),
-            none_of(" \t\r\n|()\";'#").repeated().at_least(1).to_slice(),
+                .filter(|&s| s != ".")
+                .map(str::to_owned),
         ))
-        .map(|s: &str| Value::Symbol(s.to_owned()))
+        .map(Value::Symbol)
         .labelled("symbol");
 
         let quote = just('\'')
             .ignore_then(expression.clone())
             .map(|expr| {
-                Value::Pair((
+                Value::Pair(GcCell::new((
                     Value::symbol("quote"),
                     Value::pair(&expr, &Value::empty_list()),
-                ))
+                )))
             })
             .labelled("quote");
 
         let atom = choice((boolean, character, number, string, symbol, quote));
 
-        let list = expression
-            .padded()
-            .repeated()
-            .collect::<Vec<_>>()
-            .map(|v| {
-                v.into_iter().rev().fold(Value::EmptyList, |acc, expr| {
-                    Value::Pair((expr, Gc::new(acc)))
-                })
+        let atmosphere = atmosphere(expression.clone());
+        let element = expression.clone().then_ignore(atmosphere.clone());
+
+        // The `.` before the final cdr must be followed by a delimiter, so that e.g. `.5` is
+        // still read as a number
+        let delimiter = choice((whitespace().at_least(1), one_of("()\";|").ignored()));
+        let tail = just('.')
+            .then(delimiter.rewind())
+            .ignore_then(atmosphere.clone())
+            .ignore_then(expression.clone())
+            .then_ignore(atmosphere.clone())
+            .labelled("dotted tail");
+
+        // A `.` in a vector is reported where it appears, rather than where parsing fails
+        let stray_dot = just('.')
+            .then(delimiter.rewind())
+            .validate(|_, e, emitter| {
+                emitter.emit(Rich::custom(
+                    e.span(),
+                    "vectors cannot contain a dotted tail",
+                ));
+                Value::void()
+            })
+            .then_ignore(atmosphere.clone());
+
+        let vector = atmosphere
+            .clone()
+            .ignore_then(
+                choice((element.clone(), stray_dot))
+                    .repeated()
+                    .collect::<Vec<_>>(),
+            )
+            .delimited_by(just("#("), just(')'))
+            .map(|v| Value::Vector(Gc::new(GcCell::new(v))))
+            .recover_with(via_parser(just('#').ignore_then(nested_delimiters(
+                '(',
+                ')',
+                [],
+                |_| Value::Void,
+            ))))
+            .labelled("vector");
+
+        // At least one element must precede the tail
+        let list = atmosphere
+            .clone()
+            .ignore_then(choice((
+                element
+                    .repeated()
+                    .at_least(1)
+                    .collect::<Vec<_>>()
+                    .then(tail.clone().or_not()),
+                // `(. a)` is reported, but read as if the `.` were missing
+                tail.validate(|tail, e, emitter| {
+                    emitter.emit(Rich::custom(
+                        e.span(),
+                        "a dotted list needs at least one datum before `.`",
+                    ));
+                    (Vec::new(), Some(tail))
+                }),
+                empty().to((Vec::new(), None)),
+            )))
+            .map(|(elements, tail): (Vec<_>, Option<Gc<Value>>)| {
+                let tail = tail.unwrap_or_else(Value::empty_list);
+                let list = elements
+                    .into_iter()
+                    .rev()
+                    .fold(tail, |acc, expr| Value::pair(&expr, &acc));
+                (*list).clone()
             })
             .delimited_by(just('('), just(')'))
+            // Skip to the matching `)` after an error within the list
+            .recover_with(via_parser(nested_delimiters('(', ')', [], |_| Value::Void)))
             .labelled("list");
 
-        choice((atom, list)).map(Gc::new)
+        choice((atom, vector, list)).map_with(|value, e| {
+            let value = Gc::new(value);
+            let span = e.span();
+            e.state().push((value.clone(), span));
+            value
+        })
     })
 }
 
 #[cfg(test)]
 mod tests {
+    use proptest::arbitrary::any as arbitrary;
+    use proptest::prelude::{prop, prop_assert_eq, prop_oneof, proptest, Just, Strategy};
+
     use super::*;
     use crate::scheme;
 
@@ -131,19 +468,46 @@ mod tests {
 
     #[test]
     fn parse_number() {
+        assert_parse!("+inf.0", Value::Number(f64::INFINITY));
+        assert_parse!("-inf.0", Value::Number(f64::NEG_INFINITY));
+        assert!(matches!(*parse_one("+nan.0").unwrap(), Value::Number(n) if n.is_nan()));
         assert_parse!("123456", Value::Number(123456.0));
         assert_parse!("123.456", Value::Number(123.456));
         assert_parse!("123.", Value::Number(123.0));
         assert_parse!("123.0", Value::Number(123.0));
         assert_parse!("0.456", Value::Number(0.456));
         assert_parse!(".456", Value::Number(0.456));
+        assert_parse!("1.05", Value::Number(1.05));
+        assert_parse!("007", Value::Number(7.0));
     }
 
     #[test]
     fn parse_string() {
-        assert_parse!(r#""""#, Value::String("".into()));
-        assert_parse!(r#""\t\n\\\"""#, Value::String("\t\n\\\"".into()));
-        assert_parse!(r#""a b c d""#, Value::String("a b c d".into()));
+        assert_parse!(r#""""#, Value::String(GcCell::new("".into())));
+        assert_parse!(
+            r#""\t\n\\\"""#,
+            Value::String(GcCell::new("\t\n\\\"".into()))
+        );
+        assert_parse!(r#""a b c d""#, Value::String(GcCell::new("a b c d".into())));
+        assert_parse!(
+            r#""\a\b\r\0\x41;\x3bb;""#,
+            Value::String(GcCell::new("\x07\x08\r\0Aλ".into()))
+        );
+        assert_parse!(
+            "\"one \\\n    two \\  \r\n\tthree\"",
+            Value::String(GcCell::new("one two three".into()))
+        );
+        assert_parse!("\"a\nb\"", Value::String(GcCell::new("a\nb".into())));
+        assert!(parse_one(r#""\x41""#).is_err());
+        assert!(parse_one(r#""\q""#).is_err());
+    }
+
+    #[test]
+    fn string_round_trip() {
+        for s in ["", "a \"b\" c", "\\", "\t\n\r\0\x07\x08", "\u{1}\u{85}λ"] {
+            let value = Value::string(s);
+            assert_eq!(parse_one(&value.to_string()).unwrap(), value, "{value}");
+        }
     }
 
     #[test]
@@ -151,6 +515,33 @@ mod tests {
         assert_parse!("#\\a", Value::Character('a'));
         assert_parse!("#\\space", Value::Character(' '));
         assert_parse!("#\\newline", Value::Character('\n'));
+        assert_parse!("#\\alarm", Value::Character('\x07'));
+        assert_parse!("#\\backspace", Value::Character('\x08'));
+        assert_parse!("#\\delete", Value::Character('\x7f'));
+        assert_parse!("#\\escape", Value::Character('\x1b'));
+        assert_parse!("#\\null", Value::Character('\0'));
+        assert_parse!("#\\return", Value::Character('\r'));
+        assert_parse!("#\\tab", Value::Character('\t'));
+        assert_parse!("#\\x41", Value::Character('A'));
+        assert_parse!("#\\x3bb", Value::Character('λ'));
+        assert_parse!("#\\x", Value::Character('x'));
+        assert_parse!("#\\(", Value::Character('('));
+        assert_parse!("#\\)", Value::Character(')'));
+        assert_parse!(
+            "(#\\( #\\) #\\ )",
+            *scheme!([[Value::character('(')]][[Value::character(')')]][[Value::character(' ')]])
+        );
+        assert!(parse_one("#\\xd800").is_err());
+    }
+
+    #[test]
+    fn character_round_trip() {
+        for c in [
+            'a', 'λ', '(', ')', ' ', '\n', '\0', '\x07', '\x7f', '\x1b', '\u{1}', '\u{85}',
+        ] {
+            let value = Value::character(c);
+            assert_eq!(parse_one(&value.to_string()).unwrap(), value, "{value}");
+        }
     }
 
     #[test]
@@ -161,6 +552,19 @@ mod tests {
             "!$%&*+-./:<=>?@^_~",
             Value::Symbol("!$%&*+-./:<=>?@^_~".into())
         );
+        assert_parse!("...", Value::Symbol("...".into()));
+        assert_parse!("||", Value::Symbol("".into()));
+        assert_parse!(r"|a\|b\x3bb;|", Value::Symbol("a|bλ".into()));
+    }
+
+    #[test]
+    fn symbol_round_trip() {
+        for name in [
+            "a", "", ".", "1", "-2", ".5", "+inf.0", "a b", "a|b", "#t", "'x", "\t",
+        ] {
+            let value = Value::symbol(name);
+            assert_eq!(parse_one(&value.to_string()).unwrap(), value, "{value}");
+        }
     }
 
     #[test]
@@ -172,6 +576,165 @@ mod tests {
         assert_parse!("((a) b)", *scheme!((a) b));
     }
 
+    #[test]
+    fn parse_dotted_list() {
+        let pair = |a, b| Value::pair(&a, &b);
+
+        assert_parse!("(a . b)", *pair(scheme!({ a }), scheme!({ b })));
+        assert_parse!(
+            "(1 2 . 3)",
+            *pair(scheme!({ 1 }), pair(scheme!({ 2 }), scheme!({ 3 })))
+        );
+        assert_parse!("(a . (b c))", *scheme!(a b c));
+        assert_parse!("(a . ())", *scheme!(a));
+        assert_parse!("(a .(b))", *scheme!(a b));
+        assert_parse!(
+            "( a . #| c |# b ; c\n)",
+            *pair(scheme!({ a }), scheme!({ b }))
+        );
+        assert_parse!("(a .5)", *scheme!(a 0.5));
+        assert_parse!("(a ...)", *scheme!(a [...]));
+
+        for input in [
+            "(. a)",
+            "(a . b c)",
+            "(a .)",
+            "(a . . b)",
+            "(.)",
+            "#(a . b)",
+            ".",
+        ] {
+            assert!(parse_one(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn parse_vector() {
+        assert_parse!("#()", Value::Vector(Gc::new(GcCell::new(vec![]))));
+        assert_parse!(
+            "#(a (1 2) #(b))",
+            Value::Vector(Gc::new(GcCell::new(vec![
+                scheme!({ a }),
+                scheme!(1 2),
+                Value::vector(vec![scheme!({ b })]),
+            ])))
+        );
+    }
+
+    #[test]
+    fn parse_comments() {
+        assert_parse!("; leading\n(a ; inside\n b) ; trailing", *scheme!(a b));
+        assert_parse!("(a #| block #| nested |# still |# b)", *scheme!(a b));
+        assert_parse!("(a #;(b c) d #; e)", *scheme!(a d));
+        assert_parse!("(#;#;a b c)", *scheme!(c));
+        assert_parse!(
+            "#(1 #;2 3)",
+            Value::Vector(Gc::new(GcCell::new(vec![scheme!({ 1 }), scheme!({ 3 }),])))
+        );
+        assert_parse!("#|x|#a", Value::Symbol("a".into()));
+
+        assert_eq!(
+            parse("; one\n1 #| two |# 2 #;3\n; end").unwrap(),
+            vec![scheme!({ 1 }), scheme!({ 2 })]
+        );
+        assert!(parse(";only a comment").unwrap().is_empty());
+        assert!(parse("#| unterminated").is_err());
+        assert!(parse("(a #;)").is_err());
+    }
+
+    /// Generates values that can be written and read back, i.e. anything but procedures, ports
+    /// and the like. NaN is left out as it isn't equal to itself.
+    fn readable_value() -> impl Strategy<Value = Gc<Value>> {
+        let leaf = prop_oneof![
+            arbitrary::<bool>().prop_map(Value::boolean),
+            arbitrary::<f64>()
+                .prop_filter("NaN is not equal to itself", |n| !n.is_nan())
+                .prop_map(Value::number),
+            arbitrary::<char>().prop_map(Value::character),
+            arbitrary::<String>().prop_map(|s| Value::string(&s)),
+            arbitrary::<String>().prop_map(|s| Value::symbol(&s)),
+            "[a-z+*/<=>!?.-][a-z0-9+*/<=>!?.-]{0,5}".prop_map(|s| Value::symbol(&s)),
+            Just(Value::empty_list()),
+        ];
+
+        leaf.prop_recursive(4, 32, 6, |inner| {
+            prop_oneof![
+                (inner.clone(), inner.clone()).prop_map(|(car, cdr)| Value::pair(&car, &cdr)),
+                prop::collection::vec(inner.clone(), 0..6)
+                    .prop_map(|v| Gc::new(v.into_iter().collect())),
+                prop::collection::vec(inner, 0..6).prop_map(Value::vector),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn write_round_trip(value in readable_value()) {
+            prop_assert_eq!(parse_one(&value.to_string()).unwrap(), value);
+        }
+    }
+
+    /// Parses `input`, returning the kind and position of each syntax error.
+    fn syntax_errors(input: &str) -> Vec<(SyntaxErrorKind, (usize, usize))> {
+        match parse(input) {
+            Err(Error::ParseError(errors)) => errors
+                .into_iter()
+                .map(|err| (err.kind, (err.span.line(), err.span.column())))
+                .collect(),
+            other => panic!("expected syntax errors, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn error_recovery() {
+        let errors = syntax_errors("(a . b c)\n(define x #z)\n(ok)\n\"abc\\q\" (fine)\n#(1 . 2)");
+        let positions: Vec<_> = errors.iter().map(|(_, position)| *position).collect();
+        assert_eq!(positions, [(1, 8), (2, 12), (4, 6), (5, 5)]);
+        assert_eq!(
+            errors[0].0,
+            SyntaxErrorKind::Unexpected {
+                found: Some('c'),
+                expected: vec!["')'".to_owned()],
+            }
+        );
+        assert!(matches!(errors[3].0, SyntaxErrorKind::Invalid(_)));
+
+        assert!(matches!(
+            syntax_errors("(. a)").as_slice(),
+            [(SyntaxErrorKind::Invalid(_), (1, 2))]
+        ));
+    }
+
+    #[test]
+    fn unbalanced_delimiters() {
+        assert_eq!(
+            syntax_errors("(define (f x)\n  (g x)\n(f 1)"),
+            [(SyntaxErrorKind::Unclosed("("), (1, 1))]
+        );
+        assert_eq!(
+            syntax_errors("(a) ) (b\n#(c"),
+            [
+                (SyntaxErrorKind::Unmatched, (1, 5)),
+                (SyntaxErrorKind::Unclosed("("), (1, 7)),
+                (SyntaxErrorKind::Unclosed("#("), (2, 1)),
+            ]
+        );
+        assert_eq!(
+            syntax_errors("(a \"b)"),
+            [
+                (SyntaxErrorKind::Unclosed("("), (1, 1)),
+                (SyntaxErrorKind::Unclosed("\""), (1, 4)),
+            ]
+        );
+        assert_eq!(
+            syntax_errors("a #| b #| c |#"),
+            [(SyntaxErrorKind::Unclosed("#|"), (1, 3))]
+        );
+
+        // Delimiters within strings, characters, symbols and comments don't count
+        assert!(parse("(\"(\" #\\( |)| ; (\n #| ) |#)").is_ok());
+    }
+
     #[test]
     fn parse_quote() {
         assert_parse!("'a", *scheme!(quote a));
@@ -0,0 +1,354 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, ErrorKind, Write};
+
+use gc::{Finalize, Gc, Trace};
+
+use crate::error::{Error, Result};
+use crate::parse::{parse, parse_prefix};
+use crate::value::Value;
+
+/// A source or sink of data, which is either textual, reading and writing characters, or binary,
+/// reading and writing bytes.
+///
+/// The underlying reader or writer is flushed and dropped when the port is closed, either
+/// explicitly or when the port is garbage collected. Reading from an output port or writing to
+/// an input port panics, so callers must check the direction of a port first.
+#[derive(Trace)]
+pub struct Port {
+    #[unsafe_ignore_trace]
+    direction: Direction,
+    binary: bool,
+    #[unsafe_ignore_trace]
+    state: RefCell<State>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Input,
+    Output,
+}
+
+enum State {
+    Input {
+        reader: Box<dyn BufRead>,
+        /// Characters that have been decoded, e.g. by `peek-char`, but not yet read.
+        pending: VecDeque<char>,
+        /// Whether reading may wait indefinitely for input, as for standard input or a reader
+        /// supplied by the host, which can't be asked whether input is available.
+        blocking: bool,
+    },
+    Output(Box<dyn Write>),
+    /// The output of a string port, which only ever contains valid UTF-8.
+    Buffer(Vec<u8>),
+    Closed,
+}
+
+impl Port {
+    /// Creates an input port that reads from `reader`, which may block waiting for input. As
+    /// there is no way to ask it whether input is available, `char-ready?` and `u8-ready?` only
+    /// report the port as ready once a character has been peeked.
+    pub fn input(reader: impl BufRead + 'static, binary: bool) -> Self {
+        Self::reader(Box::new(reader), binary, true)
+    }
+
+    /// Creates an input port that reads the contents of `file`, which is always ready.
+    pub fn file_input(file: File, binary: bool) -> Self {
+        Self::reader(Box::new(BufReader::new(file)), binary, false)
+    }
+
+    fn reader(reader: Box<dyn BufRead>, binary: bool, blocking: bool) -> Self {
+        Self {
+            direction: Direction::Input,
+            binary,
+            state: RefCell::new(State::Input {
+                reader,
+                pending: VecDeque::new(),
+                blocking,
+            }),
+        }
+    }
+
+    pub fn output(writer: impl Write + 'static, binary: bool) -> Self {
+        Self {
+            direction: Direction::Output,
+            binary,
+            state: RefCell::new(State::Output(Box::new(writer))),
+        }
+    }
+
+    /// Creates a textual input port that reads the contents of `string`.
+    pub fn string_input(string: &str) -> Self {
+        Self::reader(
+            Box::new(Cursor::new(string.as_bytes().to_vec())),
+            false,
+            false,
+        )
+    }
+
+    /// Creates a textual output port that accumulates the characters written to it, which can
+    /// be retrieved with [`Port::output_string`].
+    pub fn string_output() -> Self {
+        Self {
+            direction: Direction::Output,
+            binary: false,
+            state: RefCell::new(State::Buffer(Vec::new())),
+        }
+    }
+
+    pub fn is_input(&self) -> bool {
+        self.direction == Direction::Input
+    }
+
+    pub fn is_output(&self) -> bool {
+        self.direction == Direction::Output
+    }
+
+    pub fn is_binary(&self) -> bool {
+        self.binary
+    }
+
+    pub fn is_open(&self) -> bool {
+        !matches!(*self.state.borrow(), State::Closed)
+    }
+
+    /// Returns whether input is available without blocking, which is the case for files and
+    /// strings, or when a character has already been decoded.
+    pub(crate) fn is_ready(&self) -> Result<bool> {
+        match &*self.state.borrow() {
+            State::Input {
+                pending, blocking, ..
+            } => Ok(!blocking || !pending.is_empty()),
+            State::Output(_) | State::Buffer(_) => unreachable!("reading from an output port"),
+            State::Closed => Err(Error::ClosedPort),
+        }
+    }
+
+    /// Reads the next character, or returns `None` at the end of the input.
+    pub(crate) fn read_char(&self) -> Result<Option<char>> {
+        self.with_reader(|reader, pending| match pending.pop_front() {
+            Some(c) => Ok(Some(c)),
+            None => decode_char(reader),
+        })
+    }
+
+    /// Returns the next character without consuming it, or `None` at the end of the input.
+    pub(crate) fn peek_char(&self) -> Result<Option<char>> {
+        self.with_reader(|reader, pending| {
+            if pending.is_empty() {
+                pending.extend(decode_char(reader)?);
+            }
+            Ok(pending.front().copied())
+        })
+    }
+
+    /// Reads up to the next line ending, which is consumed but not included in the result.
+    pub(crate) fn read_line(&self) -> Result<Option<String>> {
+        let mut line = self.read_raw_line()?;
+
+        if let Some(line) = &mut line {
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+        }
+
+        Ok(line)
+    }
+
+    /// Reads up to and including the next line ending.
+    fn read_raw_line(&self) -> Result<Option<String>> {
+        self.with_reader(|reader, pending| {
+            let mut line = String::new();
+
+            if let Some(end) = pending.iter().position(|&c| c == '\n') {
+                line.extend(pending.drain(..=end));
+                return Ok(Some(line));
+            }
+
+            line.extend(pending.drain(..));
+            reader.read_line(&mut line)?;
+
+            Ok(Some(line).filter(|line| !line.is_empty()))
+        })
+    }
+
+    /// Reads the next datum, or returns `None` if only whitespace and comments remain.
+    ///
+    /// Input is consumed a line at a time until it contains a complete datum, so that reading
+    /// from an interactive port doesn't wait for more input than necessary. Anything following
+    /// the datum is left to be read later.
+    pub(crate) fn read(&self) -> Result<Option<Gc<Value>>> {
+        let mut input = String::new();
+
+        loop {
+            let line = self.read_raw_line()?;
+            let at_end = line.is_none();
+            input.push_str(&line.unwrap_or_default());
+
+            let datum = input.trim_start();
+            if datum.is_empty() {
+                if at_end {
+                    return Ok(None);
+                }
+                continue;
+            }
+
+            match parse_prefix(datum)? {
+                Some((value, end)) => {
+                    let rest = &datum[end..];
+                    self.with_reader(|_, pending| {
+                        pending.extend(rest.chars());
+                        Ok(())
+                    })?;
+                    return Ok(Some(value));
+                }
+                // Only comments remain
+                None if at_end && parse(datum).is_ok_and(|values| values.is_empty()) => {
+                    return Ok(None)
+                }
+                None if at_end => return Err(Error::UnexpectedEndOfInput),
+                None => continue,
+            }
+        }
+    }
+
+    /// Reads up to `k` characters, returning `None` if the input is already exhausted.
+    pub(crate) fn read_string(&self, k: usize) -> Result<Option<String>> {
+        let mut string = String::new();
+
+        for _ in 0..k {
+            match self.read_char()? {
+                Some(c) => string.push(c),
+                None if string.is_empty() => return Ok(None),
+                None => break,
+            }
+        }
+
+        Ok(Some(string))
+    }
+
+    /// Reads the next byte, or returns `None` at the end of the input.
+    pub(crate) fn read_u8(&self) -> Result<Option<u8>> {
+        self.with_reader(|reader, _| {
+            let byte = reader.fill_buf()?.first().copied();
+            if byte.is_some() {
+                reader.consume(1);
+            }
+            Ok(byte)
+        })
+    }
+
+    /// Returns the next byte without consuming it, or `None` at the end of the input.
+    pub(crate) fn peek_u8(&self) -> Result<Option<u8>> {
+        self.with_reader(|reader, _| Ok(reader.fill_buf()?.first().copied()))
+    }
+
+    pub(crate) fn write_str(&self, s: &str) -> Result<()> {
+        self.with_writer(|writer| writer.write_all(s.as_bytes()))
+    }
+
+    pub(crate) fn write_u8(&self, byte: u8) -> Result<()> {
+        self.with_writer(|writer| writer.write_all(&[byte]))
+    }
+
+    pub(crate) fn flush(&self) -> Result<()> {
+        self.with_writer(|writer| writer.flush())
+    }
+
+    /// Returns the characters written so far to a string port, or `None` if this is not a string
+    /// port.
+    pub fn output_string(&self) -> Result<Option<String>> {
+        match &*self.state.borrow() {
+            State::Buffer(buffer) => Ok(Some(String::from_utf8_lossy(buffer).into_owned())),
+            State::Closed => Err(Error::ClosedPort),
+            _ => Ok(None),
+        }
+    }
+
+    /// Closes the port, flushing any buffered output. Closing a port more than once has no effect.
+    pub fn close(&self) -> Result<()> {
+        match self.state.replace(State::Closed) {
+            State::Output(mut writer) => writer.flush().map_err(Error::io),
+            _ => Ok(()),
+        }
+    }
+
+    fn with_reader<T>(
+        &self,
+        f: impl FnOnce(&mut dyn BufRead, &mut VecDeque<char>) -> std::io::Result<T>,
+    ) -> Result<T> {
+        match &mut *self.state.borrow_mut() {
+            State::Input {
+                reader, pending, ..
+            } => f(reader, pending).map_err(Error::io),
+            State::Output(_) | State::Buffer(_) => unreachable!("reading from an output port"),
+            State::Closed => Err(Error::ClosedPort),
+        }
+    }
+
+    fn with_writer(&self, f: impl FnOnce(&mut dyn Write) -> std::io::Result<()>) -> Result<()> {
+        match &mut *self.state.borrow_mut() {
+            State::Output(writer) => f(writer).map_err(Error::io),
+            State::Buffer(buffer) => f(buffer).map_err(Error::io),
+            State::Input { .. } => unreachable!("writing to an input port"),
+            State::Closed => Err(Error::ClosedPort),
+        }
+    }
+}
+
+/// Reads a single UTF-8 encoded character.
+fn decode_char(reader: &mut dyn BufRead) -> std::io::Result<Option<char>> {
+    let Some(&first) = reader.fill_buf()?.first() else {
+        return Ok(None);
+    };
+
+    let width = match first {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    };
+
+    let mut bytes = [0; 4];
+    reader
+        .read_exact(&mut bytes[..width])
+        .map_err(|err| match err.kind() {
+            ErrorKind::UnexpectedEof => invalid_utf8(),
+            _ => err,
+        })?;
+
+    let s = std::str::from_utf8(&bytes[..width]).map_err(|_| invalid_utf8())?;
+    Ok(s.chars().next())
+}
+
+fn invalid_utf8() -> std::io::Error {
+    std::io::Error::new(ErrorKind::InvalidData, "stream did not contain valid UTF-8")
+}
+
+impl Finalize for Port {
+    fn finalize(&self) {
+        let _ = self.close();
+    }
+}
+
+impl PartialEq for Port {
+    /// Ports are only equal to themselves.
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl std::fmt::Debug for Port {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Port")
+            .field("direction", &self.direction)
+            .field("binary", &self.binary)
+            .field("open", &self.is_open())
+            .finish()
+    }
+}
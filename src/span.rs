@@ -0,0 +1,129 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::{Rc, Weak};
+
+use gc::Gc;
+
+use crate::value::Value;
+
+/// A named piece of source code, such as the contents of a file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Source {
+    name: String,
+    text: String,
+}
+
+impl Source {
+    pub fn new(name: &str, text: &str) -> Rc<Self> {
+        Rc::new(Self {
+            name: name.to_owned(),
+            text: text.to_owned(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the line and column, both starting from 1, of the character at byte `offset`.
+    /// Columns are counted in characters rather than bytes.
+    pub fn location(&self, offset: usize) -> (usize, usize) {
+        let before = &self.text[..offset.min(self.text.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        (
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+}
+
+/// The location of a datum within a [`Source`], as a range of byte offsets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub source: Rc<Source>,
+    pub range: Range<usize>,
+}
+
+impl Span {
+    /// The line on which the span starts, starting from 1.
+    pub fn line(&self) -> usize {
+        self.source.location(self.range.start).0
+    }
+
+    /// The column at which the span starts, starting from 1.
+    pub fn column(&self) -> usize {
+        self.source.location(self.range.start).1
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (line, column) = self.source.location(self.range.start);
+        write!(f, "{}:{line}:{column}", self.source.name())
+    }
+}
+
+type Spans = RefCell<HashMap<*const Value, Span>>;
+
+thread_local! {
+    /// Every span table on this thread, so that the span of a datum can be removed when it is
+    /// collected.
+    static TABLES: RefCell<Vec<Weak<Spans>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The locations of parsed data, keyed by their address.
+///
+/// The table doesn't keep the data alive. Instead, the span of a datum is removed when the
+/// garbage collector finalizes it, before its address can be reused by another value.
+#[derive(Debug, Clone)]
+pub(crate) struct SpanTable(Rc<Spans>);
+
+impl SpanTable {
+    pub(crate) fn new() -> Self {
+        let spans = Rc::new(Spans::default());
+
+        TABLES.with(|tables| {
+            let mut tables = tables.borrow_mut();
+            tables.retain(|table| table.strong_count() > 0);
+            tables.push(Rc::downgrade(&spans));
+        });
+
+        Self(spans)
+    }
+
+    pub(crate) fn insert(&self, value: &Gc<Value>, span: Span) {
+        self.0.borrow_mut().insert(&**value as *const Value, span);
+    }
+
+    pub(crate) fn get(&self, value: &Gc<Value>) -> Option<Span> {
+        self.0.borrow().get(&(&**value as *const Value)).cloned()
+    }
+
+    /// Removes the spans of the data read from the source called `name`.
+    pub(crate) fn forget_source(&self, name: &str) {
+        self.0
+            .borrow_mut()
+            .retain(|_, span| span.source.name() != name);
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+}
+
+/// Removes the span of `value` from every table on this thread, as it is about to be freed.
+pub(crate) fn forget_datum(value: &Value) {
+    // The tables may already be gone if the collector runs as the thread exits
+    let _ = TABLES.try_with(|tables| {
+        for spans in tables.borrow().iter().filter_map(Weak::upgrade) {
+            spans.borrow_mut().remove(&(value as *const Value));
+        }
+    });
+}
@@ -1,36 +1,98 @@
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
+use std::ops::Deref;
 
-use gc::{Finalize, Gc, Trace};
+use gc::{Finalize, Gc, GcCell, Trace};
 
 use crate::environment::Environment;
 use crate::error::{Error, Result};
-use crate::unscheme;
+use crate::hash_table::{Equivalence, HashTable};
+use crate::port::Port;
 
-#[derive(Debug, Clone, PartialEq, Trace, Finalize)]
+/// A Scheme value.
+///
+/// The [`PartialEq`] implementation compares values structurally, and is intended for comparing
+/// values from Rust, e.g. in tests. It is closest to `equal?`, but differs in that procedures and
+/// hash tables are compared by contents rather than identity, and it does not terminate on cyclic
+/// data. Scheme's `eq?`, `eqv?` and `equal?` predicates are implemented separately.
+#[derive(Debug, Clone, PartialEq, Trace)]
 pub enum Value {
     Void,
 
     Symbol(String),
     Number(f64),
-    String(String),
+    String(GcCell<String>),
     Character(char),
     Boolean(bool),
 
     EmptyList,
-    Pair((Gc<Value>, Gc<Value>)),
+    Pair(GcCell<(Gc<Value>, Gc<Value>)>),
+    Vector(Gc<GcCell<Vec<Gc<Value>>>>),
+
+    HashTable(Gc<GcCell<HashTable>>),
+    Port(Gc<Port>),
+    Eof,
+    Regexp(Regexp),
 
     PrimitiveProcedure(PrimitiveProcedure),
     Procedure(Procedure),
 }
 
+impl Finalize for Value {
+    fn finalize(&self) {
+        crate::span::forget_datum(self);
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Trace, Finalize)]
-pub struct PrimitiveProcedure(
-    #[unsafe_ignore_trace] pub fn(&Gc<Value>, &mut Environment) -> Result<Gc<Value>>,
-);
+pub struct PrimitiveProcedure {
+    /// The name the builtin is bound to, used in error messages.
+    #[unsafe_ignore_trace]
+    pub name: &'static str,
+    /// Called with the evaluated arguments.
+    #[unsafe_ignore_trace]
+    pub function: PrimitiveProcedureFunction,
+}
+
+pub type PrimitiveProcedureFunction = fn(&[Gc<Value>], &mut Environment) -> Result<Gc<Value>>;
+
+/// A compiled regular expression.
+///
+/// Without the `regex` feature no regexps can be created, but [`Value::Regexp`] is still present
+/// so that enabling the feature doesn't change the `Value` enum.
+#[derive(Debug, Clone, Trace, Finalize)]
+pub struct Regexp(#[unsafe_ignore_trace] pub(crate) RegexpInner);
+
+#[cfg(feature = "regex")]
+pub(crate) type RegexpInner = regex::Regex;
+
+#[cfg(not(feature = "regex"))]
+#[derive(Debug, Clone)]
+pub(crate) enum RegexpInner {}
+
+impl Regexp {
+    /// Returns the pattern the regexp was compiled from.
+    pub fn as_str(&self) -> &str {
+        #[cfg(feature = "regex")]
+        return self.0.as_str();
+        #[cfg(not(feature = "regex"))]
+        match self.0 {}
+    }
+}
+
+impl PartialEq for Regexp {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Trace, Finalize)]
 pub struct Procedure {
+    /// The name given by `(define (name ...) ...)`, used in stack traces.
+    pub name: Option<String>,
     pub parameters: Vec<String>,
+    /// Bound to a list of any arguments after those matching `parameters`.
+    pub rest: Option<String>,
     pub body: Vec<Gc<Value>>,
     pub captures: Vec<(String, Gc<Value>)>,
 }
@@ -49,7 +111,7 @@ impl Value {
     }
 
     pub fn string(s: &str) -> Gc<Self> {
-        Gc::new(Self::String(s.to_owned()))
+        Gc::new(Self::String(GcCell::new(s.to_owned())))
     }
 
     pub fn character(c: char) -> Gc<Self> {
@@ -65,16 +127,38 @@ impl Value {
     }
 
     pub fn pair(l: &Gc<Self>, r: &Gc<Self>) -> Gc<Self> {
-        Gc::new(Self::Pair((l.clone(), r.clone())))
+        Gc::new(Self::Pair(GcCell::new((l.clone(), r.clone()))))
+    }
+
+    pub fn vector(elements: Vec<Gc<Value>>) -> Gc<Self> {
+        Gc::new(Self::Vector(Gc::new(GcCell::new(elements))))
+    }
+
+    pub fn hash_table(equivalence: Equivalence) -> Gc<Self> {
+        Gc::new(Self::HashTable(Gc::new(GcCell::new(HashTable::new(
+            equivalence,
+        )))))
+    }
+
+    pub fn port(port: Port) -> Gc<Self> {
+        Gc::new(Self::Port(Gc::new(port)))
+    }
+
+    pub fn eof() -> Gc<Self> {
+        Gc::new(Self::Eof)
     }
 
     pub fn procedure(
+        name: Option<String>,
         parameters: Vec<String>,
+        rest: Option<String>,
         body: Vec<Gc<Value>>,
         captures: Vec<(String, Gc<Value>)>,
     ) -> Gc<Self> {
         Gc::new(Self::Procedure(Procedure {
+            name,
             parameters,
+            rest,
             body,
             captures,
         }))
@@ -83,24 +167,100 @@ impl Value {
     pub fn to_bool(&self) -> bool {
         !matches!(self, Self::Boolean(false))
     }
+
+    /// Iterates over the elements of a list.
+    pub fn iter(&self) -> ListIter<'_> {
+        ListIter {
+            current: Cursor::Borrowed(self),
+            slow: Cursor::Borrowed(self),
+            parity: false,
+        }
+    }
+}
+
+/// An iterator over the elements of a list, created by [`Value::iter`] or by iterating over a
+/// `&Value`, e.g. with `for element in &*list`.
+///
+/// Yields [`Error::ExpectedList`] and stops if the list is improper or circular.
+///
+/// `&Value` used to be an iterator itself, advancing by borrowing the cdr of each pair. Now that
+/// pairs are mutable that borrow could be invalidated by `set-cdr!`, so `&Value` implements
+/// [`IntoIterator`] instead: `for` loops and methods taking an `IntoIterator` work as before, and
+/// iterator adapters can be called on [`Value::iter`].
+pub struct ListIter<'a> {
+    current: Cursor<'a>,
+    // Advances at half speed, so `current` catches up to it if the list is circular
+    slow: Cursor<'a>,
+    parity: bool,
+}
+
+#[derive(Clone)]
+enum Cursor<'a> {
+    Borrowed(&'a Value),
+    Owned(Gc<Value>),
 }
 
-impl Iterator for &Value {
+impl Deref for Cursor<'_> {
+    type Target = Value;
+
+    fn deref(&self) -> &Value {
+        match self {
+            Self::Borrowed(value) => value,
+            Self::Owned(value) => value,
+        }
+    }
+}
+
+impl Cursor<'_> {
+    fn cdr(&self) -> Option<Gc<Value>> {
+        match &**self {
+            Value::Pair(pair) => Some(pair.borrow().1.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl Iterator for ListIter<'_> {
     type Item = Result<Gc<Value>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self {
-            Value::EmptyList => None,
-            Value::Pair((car, cdr)) => {
-                *self = cdr;
-                Some(Ok(car.clone()))
+        const EMPTY_LIST: &Value = &Value::EmptyList;
+
+        let (car, cdr) = match &*self.current {
+            Value::EmptyList => return None,
+            Value::Pair(pair) => pair.borrow().clone(),
+            tail => {
+                let tail = Gc::new(tail.clone());
+                self.current = Cursor::Borrowed(EMPTY_LIST);
+                return Some(Err(Error::ExpectedList(tail)));
+            }
+        };
+
+        self.current = Cursor::Owned(cdr);
+
+        self.parity = !self.parity;
+        if !self.parity {
+            if let Some(cdr) = self.slow.cdr() {
+                self.slow = Cursor::Owned(cdr);
             }
-            _ => {
-                const EMPTY_LIST: &Value = &Value::EmptyList;
-                *self = EMPTY_LIST;
-                Some(Err(Error::ExpectedList(Gc::new(self.clone()))))
+
+            if std::ptr::eq(&*self.current, &*self.slow) {
+                let pair = Gc::new((*self.current).clone());
+                self.current = Cursor::Borrowed(EMPTY_LIST);
+                return Some(Err(Error::ExpectedList(pair)));
             }
         }
+
+        Some(Ok(car))
+    }
+}
+
+impl<'a> IntoIterator for &'a Value {
+    type Item = Result<Gc<Value>>;
+    type IntoIter = ListIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
 }
 
@@ -108,63 +268,306 @@ impl FromIterator<Gc<Value>> for Value {
     fn from_iter<T: IntoIterator<Item = Gc<Value>>>(iter: T) -> Self {
         let mut elems: Vec<_> = iter.into_iter().collect();
         elems.reverse();
-        elems
-            .into_iter()
-            .fold(Value::EmptyList, |acc, e| Value::Pair((e, Gc::new(acc))))
+        elems.into_iter().fold(Value::EmptyList, |acc, e| {
+            Value::Pair(GcCell::new((e, Gc::new(acc))))
+        })
+    }
+}
+
+/// The names of characters that are written as `#\<name>`.
+pub(crate) const CHARACTER_NAMES: &[(&str, char)] = &[
+    ("alarm", '\x07'),
+    ("backspace", '\x08'),
+    ("delete", '\x7f'),
+    ("escape", '\x1b'),
+    ("newline", '\n'),
+    ("null", '\0'),
+    ("return", '\r'),
+    ("space", ' '),
+    ("tab", '\t'),
+];
+
+/// The escape sequences used in string literals, as the character following the backslash and
+/// the character it represents.
+pub(crate) const STRING_ESCAPES: &[(char, char)] = &[
+    ('a', '\x07'),
+    ('b', '\x08'),
+    ('t', '\t'),
+    ('n', '\n'),
+    ('r', '\r'),
+    ('0', '\0'),
+    ('"', '"'),
+    ('\\', '\\'),
+];
+
+/// How a value is written out by [`Value::styled`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// As by `display`: strings, characters and symbols are written as their contents.
+    Display,
+    /// As by `write`: the output can be read back in, with datum labels only where needed to
+    /// break cycles.
+    Write,
+    /// As by `write-shared`: like [`Style::Write`], but every pair or vector that appears more
+    /// than once is labelled.
+    Shared,
+}
+
+/// A value paired with the [`Style`] it should be formatted in.
+pub struct Styled<'a> {
+    value: &'a Value,
+    style: Style,
+}
+
+impl Value {
+    /// Formats the value in the given style. The [`Display`] implementation for [`Value`] uses
+    /// [`Style::Write`].
+    pub fn styled(&self, style: Style) -> Styled<'_> {
+        Styled { value: self, style }
     }
 }
 
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Void => f.write_str("#<void>"),
-            Self::Symbol(name) => {
-                if name.chars().any(char::is_whitespace) {
-                    write!(f, "|{name}|")
-                } else {
-                    f.write_str(name)
+        self.styled(Style::Write).fmt(f)
+    }
+}
+
+impl Display for Styled<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut printer = Printer {
+            style: self.style,
+            labels: HashMap::new(),
+            next_label: 0,
+        };
+        printer.find_labels(self.value, &mut HashSet::new(), &mut HashSet::new());
+        printer.print(self.value, f)
+    }
+}
+
+/// The identity of a pair or vector, used to detect shared structure.
+type Identity = *const ();
+
+fn identity(value: &Value) -> Option<Identity> {
+    match value {
+        Value::Pair(_) => Some(value as *const Value as Identity),
+        Value::Vector(elements) => Some(&**elements as *const GcCell<_> as Identity),
+        _ => None,
+    }
+}
+
+struct Printer {
+    style: Style,
+    /// Pairs and vectors that need a datum label, along with the label once it has been written.
+    labels: HashMap<Identity, Option<usize>>,
+    next_label: usize,
+}
+
+impl Printer {
+    /// Walks `value` depth first, marking the pairs and vectors that need labels: those that are
+    /// reached again while still `active`, i.e. form a cycle, and with [`Style::Shared`], those
+    /// that have been `seen` before.
+    fn find_labels(
+        &mut self,
+        value: &Value,
+        active: &mut HashSet<Identity>,
+        seen: &mut HashSet<Identity>,
+    ) {
+        let Some(id) = identity(value) else {
+            return;
+        };
+
+        if active.contains(&id) || (seen.contains(&id) && self.style == Style::Shared) {
+            self.labels.insert(id, None);
+        }
+
+        if !seen.insert(id) {
+            return;
+        }
+
+        match value {
+            Value::Pair(pair) => {
+                // Follow the cdrs iteratively so that long lists don't overflow the stack
+                let mut chain = vec![id];
+                active.insert(id);
+
+                let (car, mut cdr) = pair.borrow().clone();
+                self.find_labels(&car, active, seen);
+
+                while let Value::Pair(ref pair) = *cdr {
+                    let id = identity(&cdr).unwrap();
+
+                    if active.contains(&id) || seen.contains(&id) {
+                        self.find_labels(&cdr, active, seen);
+                        break;
+                    }
+
+                    seen.insert(id);
+                    active.insert(id);
+                    chain.push(id);
+
+                    let (car, next) = pair.borrow().clone();
+                    self.find_labels(&car, active, seen);
+                    cdr = next;
+                }
+
+                for id in chain {
+                    active.remove(&id);
+                }
+            }
+            Value::Vector(elements) => {
+                active.insert(id);
+                for element in elements.borrow().iter() {
+                    self.find_labels(element, active, seen);
+                }
+                active.remove(&id);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn print(&mut self, value: &Value, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(label) = identity(value).and_then(|id| self.labels.get_mut(&id)) {
+            match *label {
+                Some(n) => return write!(f, "#{n}#"),
+                None => {
+                    *label = Some(self.next_label);
+                    write!(f, "#{}=", self.next_label)?;
+                    self.next_label += 1;
                 }
             }
-            Self::Number(n) => f.write_str(&n.to_string()),
-            Self::String(s) => write!(f, "\"{s}\""),
-            Self::Character(c) => write!(f, "#\\{c}"),
-            Self::Boolean(b) => f.write_str(match b {
+        }
+
+        match value {
+            Value::Void => f.write_str("#<void>"),
+            Value::Symbol(name) if self.style == Style::Display || !needs_bars(name) => {
+                f.write_str(name)
+            }
+            Value::Symbol(name) => write_escaped(name, '|', f),
+            Value::Number(n) if n.is_nan() => f.write_str("+nan.0"),
+            Value::Number(n) if n.is_infinite() => f.write_str(match n.is_sign_positive() {
+                true => "+inf.0",
+                false => "-inf.0",
+            }),
+            Value::Number(n) => f.write_str(&n.to_string()),
+            Value::String(s) if self.style == Style::Display => f.write_str(&s.borrow()),
+            Value::String(s) => write_escaped(&s.borrow(), '"', f),
+            Value::Character(c) if self.style == Style::Display => write!(f, "{c}"),
+            Value::Character(c) => match CHARACTER_NAMES.iter().find(|&&(_, named)| named == *c) {
+                Some((name, _)) => write!(f, "#\\{name}"),
+                None if c.is_control() => write!(f, "#\\x{:x}", *c as u32),
+                None => write!(f, "#\\{c}"),
+            },
+            Value::Boolean(b) => f.write_str(match b {
                 true => "#t",
                 false => "#f",
             }),
-            Self::EmptyList => f.write_str("()"),
-            Self::PrimitiveProcedure(_) => f.write_str("#<procedure>"),
-            Self::Procedure(_) => f.write_str("#<procedure>"),
-            Self::Pair((car, cdr)) => {
-                if let (Ok(quote), Ok(value)) = (unscheme!(car => Symbol), unscheme!(cdr => [any]))
-                    && quote == "quote"
-                {
-                    return write!(f, "'{value}");
+            Value::EmptyList => f.write_str("()"),
+            Value::Vector(elements) => {
+                f.write_str("#(")?;
+                for (i, element) in elements.borrow().iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    self.print(element, f)?;
+                }
+                f.write_str(")")
+            }
+            Value::HashTable(_) => f.write_str("#<hash-table>"),
+            Value::Port(port) if port.is_input() => f.write_str("#<input-port>"),
+            Value::Port(_) => f.write_str("#<output-port>"),
+            Value::Eof => f.write_str("#<eof>"),
+            Value::Regexp(regexp) => write!(f, "#<regexp {}>", Value::string(regexp.as_str())),
+            Value::PrimitiveProcedure(PrimitiveProcedure { name, .. }) => {
+                write!(f, "#<procedure {name}>")
+            }
+            Value::Procedure(Procedure {
+                name: Some(name), ..
+            }) => {
+                write!(f, "#<procedure {name}>")
+            }
+            Value::Procedure(_) => f.write_str("#<procedure>"),
+            Value::Pair(pair) => {
+                let (car, cdr) = pair.borrow().clone();
+
+                if let Some(quoted) = self.quoted(&car, &cdr) {
+                    f.write_str("'")?;
+                    return self.print(&quoted, f);
                 }
 
-                write!(f, "({car}")?;
+                f.write_str("(")?;
+                self.print(&car, f)?;
 
-                let mut curr = cdr.clone();
+                let mut curr = cdr;
 
                 loop {
-                    match *curr {
-                        Self::Pair((ref car, ref cdr)) => {
-                            write!(f, " {car}")?;
-                            curr = cdr.clone();
-                        }
-                        Self::EmptyList => {
-                            f.write_str(")")?;
-                            break;
+                    let next = match *curr {
+                        Value::Pair(ref pair) if !self.is_labelled(&curr) => {
+                            let (car, cdr) = pair.borrow().clone();
+                            f.write_str(" ")?;
+                            self.print(&car, f)?;
+                            cdr
                         }
+                        Value::EmptyList => return f.write_str(")"),
                         _ => {
-                            write!(f, " . {curr})")?;
-                            break;
+                            f.write_str(" . ")?;
+                            self.print(&curr, f)?;
+                            return f.write_str(")");
                         }
-                    }
+                    };
+                    curr = next;
                 }
+            }
+        }
+    }
 
-                Ok(())
+    /// Returns the datum `x` if `car` and `cdr` form `(quote x)`, which is written as `'x`.
+    fn quoted(&self, car: &Value, cdr: &Gc<Value>) -> Option<Gc<Value>> {
+        match (car, &**cdr) {
+            (Value::Symbol(quote), Value::Pair(rest))
+                if quote == "quote" && !self.is_labelled(cdr) =>
+            {
+                let (value, end) = rest.borrow().clone();
+                matches!(*end, Value::EmptyList).then_some(value)
             }
+            _ => None,
+        }
+    }
+
+    fn is_labelled(&self, value: &Value) -> bool {
+        identity(value).is_some_and(|id| self.labels.contains_key(&id))
+    }
+}
+
+/// Whether the symbol `name` must be written between bars to be read back as the same symbol.
+fn needs_bars(name: &str) -> bool {
+    let unsigned = name.strip_prefix('-').unwrap_or(name);
+    let looks_numeric = unsigned
+        .strip_prefix('.')
+        .unwrap_or(unsigned)
+        .starts_with(|c: char| c.is_ascii_digit());
+
+    name.is_empty()
+        || name == "."
+        || looks_numeric
+        || ["+inf.0", "-inf.0", "+nan.0", "-nan.0"]
+            .iter()
+            .any(|special| name.starts_with(special))
+        || name
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "|()\";'#".contains(c))
+}
+
+/// Writes `s` between `delimiter`s, escaping the delimiter, backslashes and control characters.
+fn write_escaped(s: &str, delimiter: char, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{delimiter}")?;
+    for c in s.chars() {
+        match STRING_ESCAPES.iter().find(|&&(_, escaped)| escaped == c) {
+            Some((escape, _)) => write!(f, "\\{escape}")?,
+            None if c == delimiter => write!(f, "\\{c}")?,
+            None if c.is_control() => write!(f, "\\x{:x};", c as u32)?,
+            None => write!(f, "{c}")?,
         }
     }
+    write!(f, "{delimiter}")
 }